@@ -1,6 +1,6 @@
 # flake8: noqa F821
 
-from typing import Any, List, Dict
+from typing import Any, List, Dict, Optional, Tuple
 from sahomedb.vector import Vector, VectorID
 
 
@@ -11,11 +11,27 @@ class Config:
     - ef_construction: Nodes to consider during index construction.
     - ef_search: Nodes to consider during the search.
     - ml: Layer multiplier of the HNSW index.
+
+    Attributes:
+    - max_memory: Memory limit in bytes after which inserts are rejected.
+    - max_records: Max number of records after which inserts are rejected.
+    - eviction: Policy to evict records when a limit is reached.
     """
 
     ef_construction: int
     ef_search: int
     ml: float
+    max_memory: Optional[int]
+    max_records: Optional[int]
+    eviction: Optional[EvictionPolicy]
+    repair_every: Optional[int]
+    heuristic: Optional[Heuristic]
+    dedup: Optional[DedupPolicy]
+    dedup_distance: float
+    trash_retention: Optional[int]
+    history_retention: Optional[int]
+    default_n: int
+    max_n: Optional[int]
 
     def __init__(
         self,
@@ -32,9 +48,83 @@ class Config:
         - ef_construction: 40
         - ef_search: 15
         - ml: 0.3
+        - max_memory: None
+        - max_records: None
+        - eviction: None
+        - repair_every: None
+        - heuristic: None
+        - dedup: None
+        - dedup_distance: 0.0
+        - trash_retention: None
+        - history_retention: None
+        - default_n: 10
+        - max_n: None
+        """
+
+    def validate(self) -> None:
+        """Validates the ranges of the index parameters and the limits.
+        Collections validate the config when created or decoded.
         """
 
 
+class Heuristic:
+    """The parameters of the heuristic neighbor selection.
+    It improves the search recall on clustered data.
+
+    Args:
+    - extend_candidates: Considers the neighbors of the candidates.
+    - keep_pruned: Fills the remaining links with skipped candidates.
+    """
+
+    extend_candidates: bool
+    keep_pruned: bool
+
+    def __init__(
+        self,
+        extend_candidates: bool,
+        keep_pruned: bool,
+    ) -> None: ...
+
+
+class DedupPolicy:
+    """The policy for inserting a vector already in the collection.
+
+    Variants:
+    - Skip: Skips the insert and returns the existing vector ID.
+    - Merge: Merges the new data into the existing record.
+    """
+
+    Skip: DedupPolicy
+    Merge: DedupPolicy
+
+
+class ScoreMode:
+    """The score to report for the search results along with
+    the Euclidean distance.
+
+    Variants:
+    - Distance: Euclidean distance where lower is better.
+    - Similarity: Similarity from 0 to 1 where 1 is the same vector.
+    - Cosine: Cosine similarity from -1 to 1 where higher is better.
+    """
+
+    Distance: ScoreMode
+    Similarity: ScoreMode
+    Cosine: ScoreMode
+
+
+class EvictionPolicy:
+    """The policy to choose which record to evict from the collection.
+
+    Variants:
+    - Lru: Evicts the least recently used record.
+    - Lfu: Evicts the least frequently used record.
+    """
+
+    Lru: EvictionPolicy
+    Lfu: EvictionPolicy
+
+
 class Record:
     """The vector record to store in the collection.
 
@@ -45,6 +135,7 @@ class Record:
     Metadata types:
     - String
     - Number
+    - None
     - List of metadata types
     - Dictionary of metadata types
     """
@@ -78,7 +169,13 @@ class Collection:
 
     config: Config
 
-    def __init__(self, config: Config) -> None: ...
+    def __init__(self, config: Config) -> None:
+        """Creates an empty collection with the given configuration.
+        Raises an error if the configuration is invalid.
+
+        Args:
+        - config: Collection configuration.
+        """
 
     @staticmethod
     def from_records(
@@ -92,20 +189,66 @@ class Collection:
         - records: Records used to build the collection.
         """
 
-    def insert(self, record: Record) -> None:
-        """Inserts a record into the collection.
+    def insert(self, record: Record) -> VectorID:
+        """Inserts a record into the collection and returns
+        its vector ID. Slots of deleted records are reused.
 
         Args:
         - record: Record to insert.
         """
 
     def delete(self, id: VectorID) -> None:
-        """Deletes a record from the collection.
+        """Deletes a record from the collection. If the trash is
+        enabled in the config, the record can be restored until its
+        retention ends.
 
         Args:
         - id: Vector ID to delete.
         """
 
+    def trash(self) -> Dict[VectorID, Record]:
+        """Returns the deleted records in the trash by the vector ID
+        they had before the deletion.
+        """
+
+    def restore(self, id: VectorID) -> VectorID:
+        """Restores the deleted record from the trash and returns its
+        new vector ID which may differ from the one before the deletion.
+
+        Args:
+        - id: Vector ID of the record before the deletion.
+        """
+
+    def purge_trash(self) -> int:
+        """Permanently deletes the records kept in the trash longer
+        than the retention and returns the number of purged records.
+        """
+
+    def repair(self) -> int:
+        """Re-links the records that became unreachable in the index
+        after deletions and returns the number of re-linked records.
+        """
+
+    def unreachable_records(self) -> List[VectorID]:
+        """Returns the records that can't be reached from the
+        entry point of the index.
+        """
+
+    def save_to(self, path: str) -> None:
+        """Saves the collection to the file in a compact binary format.
+
+        Args:
+        - path: File path to write to.
+        """
+
+    @staticmethod
+    def load_from(path: str) -> Collection:
+        """Loads a collection saved with save_to from the file.
+
+        Args:
+        - path: File path to read from.
+        """
+
     def get(self, id: VectorID) -> Record:
         """Returns a record from the collection.
 
@@ -126,13 +269,107 @@ class Collection:
         - record: New record.
         """
 
-    def search(self, vector: Vector, n: int) -> List[SearchResult]:
+    def upsert(self, id: VectorID, record: Record) -> bool:
+        """Updates the record with the vector ID if it exists or
+        inserts it with the vector ID otherwise. Returns True if
+        the record was created. A duplicate record isn't created
+        and the vector ID can skip at most 65536 slots.
+
+        Args:
+        - id: Vector ID of the record.
+        - record: Record to update or insert.
+        """
+
+    def search(
+        self,
+        vector: Vector,
+        n: Optional[int] = None,
+    ) -> List[SearchResult]:
         """Searches for the nearest neighbors to
         the given vector using HNSW indexing algorithm
 
+        Args:
+        - vector: Vector to search.
+        - n: Number of neighbors to return. Defaults to default_n.
+        """
+
+    def search_with_entry_points(
+        self,
+        vector: Vector,
+        n: int,
+        num_entry_points: int,
+    ) -> List[SearchResult]:
+        """Searches for the nearest neighbors like search but starts
+        from multiple records spread across the collection. This
+        improves recall on clustered data.
+
+        Args:
+        - vector: Vector to search.
+        - n: Number of neighbors to return.
+        - num_entry_points: Number of entry points.
+        """
+
+    def search_with_budget(
+        self,
+        vector: Vector,
+        n: int,
+        budget_ms: int,
+    ) -> Tuple[List[SearchResult], bool]:
+        """Searches for the nearest neighbors like search but stops
+        once the time budget is spent. Returns the nearest records
+        found so far and True if the search was cut short.
+
+        Args:
+        - vector: Vector to search.
+        - n: Number of neighbors to return.
+        - budget_ms: Time budget of the search in milliseconds.
+        """
+
+    def search_scored(
+        self,
+        vector: Vector,
+        n: int,
+        mode: ScoreMode,
+    ) -> List[SearchResult]:
+        """Searches for the nearest neighbors like search and
+        scores the results with the score mode.
+
         Args:
         - vector: Vector to search.
         - n: Number of neighbors to return.
+        - mode: How to score the results.
+        """
+
+    def create_text_index(self, fields: List[str]) -> None:
+        """Creates a full-text index over the text in the data fields.
+        Data that isn't a dictionary is indexed as a whole.
+
+        Args:
+        - fields: Names of the data fields to index.
+        """
+
+    def text_search(self, query: str, n: int) -> List[SearchResult]:
+        """Searches for the records most relevant to the query
+        using BM25. The result distance is 1 / (1 + score).
+
+        Args:
+        - query: Text to search for.
+        - n: Number of records to return.
+        """
+
+    def hybrid_search(
+        self,
+        vector: Vector,
+        query: str,
+        n: int,
+    ) -> List[SearchResult]:
+        """Searches using both the vector and the text query and
+        fuses the rankings using reciprocal rank fusion.
+
+        Args:
+        - vector: Vector to search.
+        - query: Text to search for.
+        - n: Number of records to return.
         """
 
     def true_search(self, vector: Vector, n: int) -> List[SearchResult]:
@@ -148,15 +385,73 @@ class Collection:
 
     def set_dimension(self, dimension: int) -> None:
         """Sets the vector dimension of the collection.
-        The collection must be empty to do this.
+        The collection must be empty to do this. Changing the
+        dimension drops the trash and the record versions.
 
         Args:
         - dimension: Vector dimension.
         """
 
+    def set_read_only(self, read_only: bool) -> None:
+        """Freezes or unfreezes the collection. A read-only
+        collection rejects inserts, updates, and deletes.
+
+        Args:
+        - read_only: True to freeze the collection.
+        """
+
+    def is_read_only(self) -> bool:
+        """Returns True if the collection is read-only."""
+
+    def set_model(self, model: Optional[str]) -> None:
+        """Tags the collection with the embedding model of its vectors.
+        Vectors from different models aren't comparable.
+
+        Args:
+        - model: Model identifier or None to remove the tag.
+        """
+
+    def model(self) -> Optional[str]:
+        """Returns the embedding model the collection is tagged with."""
+
+    def ensure_model(self, model: str) -> None:
+        """Raises an error if the model doesn't match the model of
+        the collection. Untagged collections accept any model.
+
+        Args:
+        - model: Model identifier of the vectors or the query.
+        """
+
+    def insert_with_model(self, record: Record, model: str) -> VectorID:
+        """Inserts the record if its vector is from the embedding
+        model of the collection.
+
+        Args:
+        - record: Record to insert.
+        - model: Model identifier of the vector.
+        """
+
+    def search_with_model(
+        self,
+        vector: Vector,
+        n: int,
+        model: str,
+    ) -> List[SearchResult]:
+        """Searches for the nearest neighbors if the query is from
+        the embedding model of the collection.
+
+        Args:
+        - vector: Vector to search.
+        - n: Number of neighbors to return.
+        - model: Model identifier of the query vector.
+        """
+
     def len(self) -> int:
         """Returns the number of records in the collection."""
 
+    def memory_usage(self) -> int:
+        """Returns the estimated memory usage of the collection in bytes."""
+
     def is_empty(self) -> bool:
         """Returns True if the collection is empty."""
 
@@ -169,4 +464,5 @@ class SearchResult:
 
     id: int
     distance: float
+    score: float
     data: Any
\ No newline at end of file
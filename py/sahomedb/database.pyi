@@ -1,6 +1,8 @@
 # flake8: noqa F821
 
-from sahomedb.collection import Collection, Record, Config
+from typing import List, Tuple
+from sahomedb.collection import Collection, Record, Config, SearchResult
+from sahomedb.vector import Vector
 
 
 class Database:
@@ -20,6 +22,12 @@ class Database:
         - path: Path to the database file.
         """
 
+    @staticmethod
+    def in_memory() -> Database:
+        """Creates a database that keeps the collections in memory.
+        The collections are lost when the database is dropped.
+        """
+
     def get_collection(self, name: str) -> Collection:
         """Returns the collection with the given name.
 
@@ -29,6 +37,7 @@ class Database:
 
     def save_collection(self, name: str, collection: Collection) -> None:
         """Saves new or update existing collection to the database.
+        Names starting with a null character are reserved.
 
         Args:
         - name: Collection name.
@@ -42,6 +51,68 @@ class Database:
         - name: Collection name.
         """
 
+    def export_collection(self, name: str, path: str) -> None:
+        """Writes the collection to a single file with its config,
+        records, and indexes to import into another database.
+
+        Args:
+        - name: Collection name.
+        - path: File path to write to.
+        """
+
+    def import_collection(self, name: str, path: str) -> None:
+        """Saves the collection from a file written by export_collection
+        or Collection.save_to. Other collections are left as is.
+
+        Args:
+        - name: Name to save the collection as.
+        - path: File path to read from.
+        """
+
+    def search_collections(
+        self,
+        names: List[str],
+        vector: Vector,
+        n: int,
+    ) -> List[Tuple[str, SearchResult]]:
+        """Searches multiple collections for the nearest neighbors
+        and merges the results by distance. Each result is paired
+        with the name of the collection it's from.
+
+        Args:
+        - names: Names of the collections to search.
+        - vector: Vector to search.
+        - n: Number of neighbors to return.
+        """
+
+    def stage_collection(self, name: str, collection: Collection) -> None:
+        """Saves a new version of the collection to be promoted later.
+        The current version keeps serving reads until the promotion.
+
+        Args:
+        - name: Collection name.
+        - collection: New version of the collection.
+        """
+
+    def promote_collection(self, name: str) -> None:
+        """Replaces the collection with its staged version atomically.
+        The replaced version is kept for a rollback.
+
+        Args:
+        - name: Collection name.
+        """
+
+    def rollback_collection(self, name: str) -> None:
+        """Swaps the collection with the version replaced by the
+        last promotion.
+
+        Args:
+        - name: Collection name.
+        """
+
+    def flush(self) -> None:
+        """Ensures the saved collections are persisted to the storage."""
+
     def len(self) -> int:
         """Returns the number of collections in the database."""
 
@@ -1,4 +1,7 @@
+import os
 from sahomedb.prelude import Config, Record, Collection, Vector, VectorID
+from sahomedb.prelude import DedupPolicy, EvictionPolicy, Heuristic
+from sahomedb.prelude import ScoreMode
 
 DIMENSION = 128
 LEN = 100
@@ -23,6 +26,32 @@ def test_create_config():
     assert config.ml == default.ml
 
 
+def test_validate_config():
+    config = Config.create_default()
+    config.ml = 1.0
+
+    try:
+        config.validate()
+        assert False
+    except Exception as e:
+        assert "invalid collection config" in str(e).lower()
+
+    try:
+        Collection(config=config)
+        assert False
+    except Exception as e:
+        assert "invalid collection config" in str(e).lower()
+
+
+def test_build_collection_heuristic():
+    config = Config.create_default()
+    config.heuristic = Heuristic(extend_candidates=False, keep_pruned=True)
+
+    records = Record.many_random(dimension=DIMENSION, len=LEN)
+    collection = Collection.from_records(config=config, records=records)
+    assert collection.len() == LEN
+
+
 def test_create_record():
     vector = [0.1, 0.2, 0.3]
     data = {"text": "This is an example."}
@@ -60,10 +89,11 @@ def test_build_collection():
 def test_insert_record():
     collection = create_test_collection()
     record = Record.random(dimension=128)
-    collection.insert(record)
+    id = collection.insert(record)
 
     assert collection.len() == LEN + 1
     assert collection.contains(VectorID(LEN))
+    assert collection.get(id).data == record.data
 
 
 def test_insert_record_invalid_dimension():
@@ -81,6 +111,47 @@ def test_insert_record_invalid_dimension():
     assert collection.len() == LEN
 
 
+def test_insert_record_non_finite_value():
+    collection = create_test_collection()
+    vector = [0.0] * DIMENSION
+    vector[3] = float("nan")
+    record = Record(vector=vector, data=None)
+
+    try:
+        collection.insert(record)
+        assert False
+    except Exception as e:
+        assert "invalid vector value" in str(e).lower()
+
+    assert collection.len() == LEN
+
+
+def test_insert_duplicate_record():
+    config = Config.create_default()
+    config.dedup = DedupPolicy.Skip
+    collection = Collection(config=config)
+
+    record = Record.random(dimension=DIMENSION)
+    collection.insert(record)
+    collection.insert(record)
+
+    assert collection.len() == 1
+
+
+def test_read_only_collection():
+    collection = create_test_collection()
+    collection.set_read_only(True)
+
+    try:
+        collection.insert(Record.random(dimension=DIMENSION))
+        assert False
+    except Exception as e:
+        assert "read-only" in str(e).lower()
+
+    assert collection.is_read_only()
+    assert collection.len() == LEN
+
+
 def test_delete_record():
     collection = create_test_collection()
 
@@ -91,6 +162,46 @@ def test_delete_record():
     assert collection.len() == LEN - 1
 
 
+def test_delete_and_restore_record():
+    config = Config.create_default()
+    config.trash_retention = 60
+    records = Record.many_random(dimension=DIMENSION, len=LEN)
+    collection = Collection.from_records(config=config, records=records)
+
+    id = VectorID(0)
+    collection.delete(id)
+    assert len(collection.trash()) == 1
+
+    new_id = collection.restore(id)
+    assert collection.contains(new_id)
+    assert collection.len() == LEN
+    assert len(collection.trash()) == 0
+
+
+def test_repair_after_delete():
+    config = Config.create_default()
+    config.repair_every = 1
+    records = Record.many_random(dimension=DIMENSION, len=LEN)
+    collection = Collection.from_records(config=config, records=records)
+
+    for i in range(LEN // 2):
+        collection.delete(VectorID(i))
+
+    assert len(collection.unreachable_records()) == 0
+    assert collection.repair() == 0
+
+
+def test_save_and_load_collection():
+    collection = create_test_collection()
+    path = "data/112.bin"
+
+    os.makedirs("data", exist_ok=True)
+    collection.save_to(path)
+    loaded = Collection.load_from(path)
+
+    assert loaded.len() == collection.len()
+
+
 def test_get_record():
     collection = create_test_collection()
 
@@ -112,6 +223,17 @@ def test_update_record():
     assert collection.get(id).data == record.data
 
 
+def test_upsert_record():
+    collection = create_test_collection()
+    record = Record.random(dimension=DIMENSION)
+
+    assert not collection.upsert(VectorID(0), record)
+    assert collection.upsert(VectorID(LEN + 3), record)
+
+    assert collection.contains(VectorID(LEN + 3))
+    assert collection.len() == LEN + 1
+
+
 def test_search_record():
     collection = create_test_collection()
     vector = Vector.random(dimension=DIMENSION)
@@ -129,6 +251,64 @@ def test_search_record():
     assert results[0].id in [true.id for true in true_results]
 
 
+def test_search_default_and_max_n():
+    config = Config.create_default()
+    config.default_n = 5
+    config.max_n = 8
+    records = Record.many_random(dimension=DIMENSION, len=LEN)
+    collection = Collection.from_records(config=config, records=records)
+    vector = Vector.random(dimension=DIMENSION)
+
+    assert len(collection.search(vector)) == 5
+
+    try:
+        collection.search(vector, n=9)
+        assert False
+    except Exception as e:
+        assert "over the limit" in str(e).lower()
+
+
+def test_search_with_entry_points():
+    collection = create_test_collection()
+    vector = Vector.random(dimension=DIMENSION)
+
+    results = collection.search_with_entry_points(vector, 10, 4)
+    assert len(results) == 10
+
+
+def test_search_with_budget():
+    collection = create_test_collection()
+    vector = Vector.random(dimension=DIMENSION)
+
+    results, partial = collection.search_with_budget(vector, 10, 1000)
+    assert len(results) == 10
+    assert not partial
+
+
+def test_search_scored():
+    collection = create_test_collection()
+    vector = Vector.random(dimension=DIMENSION)
+
+    results = collection.search_scored(vector, n=10, mode=ScoreMode.Similarity)
+    assert len(results) == 10
+    assert all(0 <= result.score <= 1 for result in results)
+
+
+def test_text_search():
+    config = Config.create_default()
+    collection = Collection(config=config)
+    collection.create_text_index(fields=["text"])
+
+    texts = ["A quick brown fox.", "Embedded vector database."]
+    for text in texts:
+        vector = Vector.random(dimension=DIMENSION).to_list()
+        collection.insert(Record(vector=vector, data={"text": text}))
+
+    results = collection.text_search("databases", n=5)
+    assert len(results) == 1
+    assert results[0].data["text"] == texts[1]
+
+
 def test_set_dimension():
     config = Config.create_default()
     collection = Collection(config=config)
@@ -147,6 +327,55 @@ def test_set_dimension():
         assert "invalid vector dimension" in str(e).lower()
 
 
+def test_model_mismatch():
+    collection = create_test_collection()
+    collection.set_model("model-a")
+    record = Record.random(dimension=DIMENSION)
+    collection.insert_with_model(record, "model-a")
+
+    try:
+        collection.insert_with_model(record, "model-b")
+        assert False
+    except Exception as e:
+        assert "invalid embedding model" in str(e).lower()
+
+    assert collection.len() == LEN + 1
+
+
+def test_memory_usage():
+    collection = create_test_collection()
+    usage = collection.memory_usage()
+    assert usage > DIMENSION * LEN * 4
+
+
+def test_insert_record_memory_limit():
+    config = Config.create_default()
+    config.max_memory = 1
+    collection = Collection(config=config)
+
+    # Inserts should be rejected once the limit is reached.
+    try:
+        collection.insert(Record.random(dimension=DIMENSION))
+        assert False
+    except Exception as e:
+        assert "memory limit" in str(e).lower()
+
+
+def test_insert_record_eviction():
+    config = Config.create_default()
+    config.max_records = 1
+    config.eviction = EvictionPolicy.Lru
+    collection = Collection(config=config)
+
+    # The first record is evicted to make room for the second.
+    collection.insert(Record.random(dimension=DIMENSION))
+    record = Record.random(dimension=DIMENSION)
+    id = collection.insert(record)
+
+    assert collection.len() == 1
+    assert collection.get(id).data == record.data
+
+
 def test_list_records():
     collection = create_test_collection()
     records = collection.list()
@@ -1,4 +1,4 @@
-from sahomedb.prelude import Record, Collection, Config, Database
+from sahomedb.prelude import Record, Collection, Config, Database, Vector
 
 
 NAME = "vectors"  # Initial collection name.
@@ -52,6 +52,50 @@ def test_save_collection():
     assert db.len() == 2
 
 
+def test_in_memory():
+    db = Database.in_memory()
+    collection = Collection(config=Config.create_default())
+    db.save_collection(name=NAME, collection=collection)
+    db.flush()
+
+    assert db.len() == 1
+
+
+def test_search_collections():
+    db = create_test_database(path="data/106")
+    records = Record.many_random(dimension=DIMENSION, len=LEN)
+    collection = Collection.from_records(Config.create_default(), records)
+    db.save_collection(name="other", collection=collection)
+
+    query = Vector.random(dimension=DIMENSION)
+    results = db.search_collections([NAME, "other"], query, n=10)
+
+    assert len(results) == 10
+    assert all(name in [NAME, "other"] for name, _ in results)
+
+
+def test_export_and_import_collection():
+    db = create_test_database(path="data/108")
+    db.export_collection(name=NAME, path="data/108.bin")
+
+    other = Database.new(path="data/109")
+    other.import_collection(name="copy", path="data/108.bin")
+    assert other.get_collection(name="copy").len() == LEN
+
+
+def test_promote_collection():
+    db = create_test_database(path="data/107")
+    records = Record.many_random(dimension=DIMENSION, len=10)
+    collection = Collection.from_records(Config.create_default(), records)
+
+    db.stage_collection(name=NAME, collection=collection)
+    db.promote_collection(name=NAME)
+    assert db.get_collection(name=NAME).len() == 10
+
+    db.rollback_collection(name=NAME)
+    assert db.get_collection(name=NAME).len() == LEN
+
+
 def test_delete_collection():
     db = create_test_database(path="data/105")
     db.delete_collection(name=NAME)
@@ -0,0 +1,541 @@
+use super::*;
+
+/// The key-value storage used to persist the collections.
+/// Implement this trait to use a custom storage engine.
+pub trait StorageBackend: Send + Sync {
+    /// Returns the value associated with the key.
+    /// * `key` - Key to retrieve.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Inserts or replaces the value associated with the key.
+    /// * `key` - Key to store the value under.
+    /// * `value` - Value to store.
+    fn put(&mut self, key: &str, value: &[u8]) -> Result<(), Error>;
+
+    /// Removes the key and its value if it exists.
+    /// * `key` - Key to remove.
+    fn delete(&mut self, key: &str) -> Result<(), Error>;
+
+    /// Returns the key-value pairs with keys starting with the prefix
+    /// sorted by key. Use an empty prefix to return all pairs.
+    /// * `prefix` - Prefix of the keys to return.
+    fn scan(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, Error>;
+
+    /// Ensures the written values are persisted.
+    fn flush(&self) -> Result<(), Error>;
+
+    /// Returns the keys starting with the prefix sorted by key
+    /// without reading their values.
+    /// * `prefix` - Prefix of the keys to return.
+    fn keys(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let pairs = self.scan(prefix)?;
+        Ok(pairs.into_iter().map(|(key, _)| key).collect())
+    }
+
+    /// Applies multiple writes where None deletes the key.
+    /// The default implementation applies them one by one which is
+    /// only atomic if the writes can't fail. Backends with writes
+    /// that can fail must override this to apply them atomically.
+    /// * `batch` - Key and value pairs to write.
+    fn apply_batch(
+        &mut self,
+        batch: Vec<(String, Option<Vec<u8>>)>,
+    ) -> Result<(), Error> {
+        for (key, value) in batch {
+            match value {
+                Some(value) => self.put(&key, &value)?,
+                None => self.delete(&key)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the key exists in the storage.
+    /// * `key` - Key to check.
+    fn contains(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Returns the number of keys in the storage.
+    fn len(&self) -> Result<usize, Error> {
+        Ok(self.keys("")?.len())
+    }
+
+    /// Returns true if the storage has no keys.
+    fn is_empty(&self) -> Result<bool, Error> {
+        Ok(self.len()? == 0)
+    }
+}
+
+/// Storage backend keeping the values in memory only.
+/// The values are lost when the backend is dropped.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    values: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemoryBackend {
+    /// Creates an empty in-memory storage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.values.get(key).cloned())
+    }
+
+    fn put(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.values.insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), Error> {
+        self.values.remove(key);
+        Ok(())
+    }
+
+    fn scan(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let pairs = self
+            .values
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        Ok(pairs)
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn keys(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let keys = self
+            .values
+            .range(prefix.to_string()..)
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(prefix))
+            .cloned()
+            .collect();
+
+        Ok(keys)
+    }
+
+    fn contains(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.values.contains_key(key))
+    }
+
+    fn len(&self) -> Result<usize, Error> {
+        Ok(self.values.len())
+    }
+}
+
+/// Max length of the file names on common file systems.
+const MAX_FILE_NAME: usize = 255;
+/// Extension of the file written before it replaces the value.
+const TMP_EXTENSION: &str = "tmp";
+/// File listing the writes of the batch being applied.
+const JOURNAL: &str = "journal";
+
+/// Storage backend writing each value to its own file.
+/// The file names are hex-encoded keys to keep them valid
+/// which limits the keys to `FileBackend::MAX_KEY_LEN` bytes.
+#[derive(Debug)]
+pub struct FileBackend {
+    dir: PathBuf,
+}
+
+impl FileBackend {
+    /// Max length of the keys in bytes. The hex-encoded key and
+    /// the temporary file extension must fit in the file name.
+    pub const MAX_KEY_LEN: usize =
+        (MAX_FILE_NAME - TMP_EXTENSION.len() - 1) / 2;
+
+    /// Opens the storage directory, creating it if it doesn't exist.
+    /// * `path` - Directory to store the files.
+    pub fn open(path: &str) -> Result<Self, Error> {
+        create_dir_all(path)?;
+        let backend = Self { dir: PathBuf::from(path) };
+        backend.recover()?;
+        Ok(backend)
+    }
+
+    /// Finishes the batch interrupted after its journal was written
+    /// and removes the temporary files of the unfinished writes.
+    fn recover(&self) -> Result<(), Error> {
+        let journal = self.dir.join(JOURNAL);
+        if journal.is_file() {
+            let entries = read_to_string(&journal)?;
+            let entries = entries.lines().filter_map(|l| l.split_once(' '));
+            let entries: Vec<(String, bool)> = entries
+                .map(|(op, name)| (name.to_string(), op == "put"))
+                .collect();
+            self.commit(&entries)?;
+        }
+
+        for entry in read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == TMP_EXTENSION) {
+                remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves the temporary files of the batch into place, deletes
+    /// the removed keys, and then removes the journal. Files already
+    /// moved are skipped so the batch can be committed again.
+    /// * `entries` - File names and true for puts, false for deletes.
+    fn commit(&self, entries: &[(String, bool)]) -> Result<(), Error> {
+        for (name, put) in entries {
+            let path = self.dir.join(name);
+            let result = match put {
+                true => rename(path.with_extension(TMP_EXTENSION), path),
+                false => remove_file(path),
+            };
+
+            if let Err(err) = result {
+                if err.kind() != ErrorKind::NotFound {
+                    return Err(err.into());
+                }
+            }
+        }
+
+        remove_file(self.dir.join(JOURNAL))?;
+        Ok(())
+    }
+
+    /// Writes the value to the temporary file of the path.
+    fn write_tmp(path: &Path, value: &[u8]) -> Result<PathBuf, Error> {
+        let tmp = path.with_extension(TMP_EXTENSION);
+        let mut file = File::create(&tmp)?;
+        file.write_all(value)?;
+        file.sync_all()?;
+        Ok(tmp)
+    }
+
+    /// Returns the file path storing the value of the key.
+    /// None if the key is too long to be stored.
+    fn path(&self, key: &str) -> Option<PathBuf> {
+        if key.len() > Self::MAX_KEY_LEN {
+            return None;
+        }
+
+        let name: String = key.bytes().map(|b| format!("{b:02x}")).collect();
+        Some(self.dir.join(name))
+    }
+
+    /// Returns the file path to write the value of the key to.
+    /// Returns an error if the key is too long to be stored.
+    fn writable_path(&self, key: &str) -> Result<PathBuf, Error> {
+        self.path(key)
+            .ok_or_else(|| Error::key_too_long(key.len(), Self::MAX_KEY_LEN))
+    }
+
+    /// Returns the decoded keys of the files in the directory.
+    fn stored_keys(&self) -> Result<Vec<(String, PathBuf)>, Error> {
+        let mut keys = vec![];
+        for entry in read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+
+            // Skip temporary files and files not created by the backend.
+            if let Some(key) = name.to_str().and_then(Self::decode) {
+                keys.push((key, entry.path()));
+            }
+        }
+
+        keys.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(keys)
+    }
+
+    /// Decodes the key from the file name if it's a valid one.
+    fn decode(name: &str) -> Option<String> {
+        let byte = |i: usize| u8::from_str_radix(name.get(i..i + 2)?, 16).ok();
+        let bytes: Option<Vec<u8>> =
+            (0..name.len()).step_by(2).map(byte).collect();
+        String::from_utf8(bytes?).ok()
+    }
+}
+
+impl StorageBackend for FileBackend {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        // Keys that are too long can't be stored.
+        let path = match self.path(key) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        match read(path) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn put(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let path = self.writable_path(key)?;
+
+        // Write to a temporary file first so that a crash
+        // mid-write doesn't corrupt the existing value.
+        let tmp = Self::write_tmp(&path, value)?;
+        rename(tmp, path)?;
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), Error> {
+        let path = match self.path(key) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        match remove_file(path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn scan(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let keys = self.stored_keys()?.into_iter();
+        let keys = keys.filter(|(key, _)| key.starts_with(prefix));
+        keys.map(|(key, path)| Ok((key, read(path)?))).collect()
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        // The values are synced to the disk on write.
+        Ok(())
+    }
+
+    /// Applies the batch atomically using a journal. The values are
+    /// written to temporary files first, then the journal listing the
+    /// writes is created which commits the batch. If the process stops
+    /// before the journal exists, none of the writes are applied.
+    /// Otherwise, the batch is finished when the backend is opened.
+    fn apply_batch(
+        &mut self,
+        batch: Vec<(String, Option<Vec<u8>>)>,
+    ) -> Result<(), Error> {
+        // Check all keys before writing anything.
+        let paths = batch.iter().map(|(key, _)| self.writable_path(key));
+        let paths = paths.collect::<Result<Vec<PathBuf>, Error>>()?;
+
+        let mut entries = Vec::with_capacity(batch.len());
+        let mut journal = String::new();
+        for ((_, value), path) in batch.iter().zip(paths) {
+            if let Some(value) = value {
+                Self::write_tmp(&path, value)?;
+            }
+
+            let name = path.file_name().unwrap().to_string_lossy();
+            let op = if value.is_some() { "put" } else { "delete" };
+            journal.push_str(&format!("{op} {name}\n"));
+            entries.push((name.to_string(), value.is_some()));
+        }
+
+        let path = self.dir.join(JOURNAL);
+        let tmp = Self::write_tmp(&path, journal.as_bytes())?;
+        rename(tmp, path)?;
+        self.commit(&entries)
+    }
+
+    fn keys(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let keys = self.stored_keys()?.into_iter().map(|(key, _)| key);
+        Ok(keys.filter(|key| key.starts_with(prefix)).collect())
+    }
+
+    fn contains(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.path(key).is_some_and(|path| path.is_file()))
+    }
+
+    fn len(&self) -> Result<usize, Error> {
+        Ok(self.stored_keys()?.len())
+    }
+}
+
+/// File in the database directory locked by the process using it.
+/// It stores the process ID to name the holder in the error.
+const PID_FILE: &str = "sahomedb.pid";
+
+/// The advisory lock on a database directory held while it's open.
+/// The lock file is removed when the lock is dropped.
+#[derive(Debug)]
+pub struct DirLock {
+    dir: PathBuf,
+    // Kept open since closing the file releases the lock.
+    file: File,
+}
+
+impl DirLock {
+    /// Locks the directory and creates it if it doesn't exist.
+    /// * `path` - Directory of the database.
+    pub fn acquire(path: &str) -> Result<Self, Error> {
+        create_dir_all(path)?;
+        let dir = PathBuf::from(path);
+        let pid_file = dir.join(PID_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&pid_file)?;
+
+        if let Err(err) = file.try_lock_exclusive() {
+            if err.kind() == lock_contended_error().kind() {
+                let pid = read_to_string(&pid_file).unwrap_or_default();
+                return Err(Error::database_locked(path, pid.trim()));
+            }
+
+            return Err(err.into());
+        }
+
+        file.set_len(0)?;
+        write!(file, "{}", process::id())?;
+        Ok(Self { dir, file })
+    }
+
+    /// Removes everything in the directory except the lock file.
+    pub fn clear(&self) -> Result<(), Error> {
+        for entry in read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.file_name().is_some_and(|name| name == PID_FILE) {
+                continue;
+            }
+
+            match path.is_dir() {
+                true => remove_dir_all(path)?,
+                false => remove_file(path)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the locked directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        // The file itself is kept since removing it while locked would
+        // let another process lock a new file at the same path.
+        // The lock is released when the file is closed after this.
+        let _ = self.file.set_len(0);
+    }
+}
+
+/// Storage backend using Sled, the default storage engine.
+#[derive(Debug)]
+pub struct SledBackend {
+    // Fields drop in order so Sled closes before the lock is released.
+    db: Db,
+    _lock: DirLock,
+}
+
+impl SledBackend {
+    /// Opens existing or creates new Sled database.
+    /// * `path` - Directory to store the database.
+    pub fn open(path: &str) -> Result<Self, Error> {
+        Self::open_with_flush_interval(path, Some(500))
+    }
+
+    /// Opens the Sled database with a custom flush interval.
+    /// Writes within the interval are grouped into one disk sync.
+    /// * `path` - Directory to store the database.
+    /// * `flush_every_ms` - Max milliseconds before the writes are
+    ///   synced. None to only sync on explicit flush.
+    pub fn open_with_flush_interval(
+        path: &str,
+        flush_every_ms: Option<u64>,
+    ) -> Result<Self, Error> {
+        let lock = DirLock::acquire(path)?;
+        Self::open_locked(lock, flush_every_ms)
+    }
+
+    /// Opens the Sled database in the directory already locked.
+    /// * `lock` - Lock of the directory to store the database.
+    /// * `flush_every_ms` - Max milliseconds before the writes are
+    ///   synced. None to only sync on explicit flush.
+    pub fn open_locked(
+        lock: DirLock,
+        flush_every_ms: Option<u64>,
+    ) -> Result<Self, Error> {
+        // Using sled::Config to prevent name collisions
+        // with collection's Config.
+        let config =
+            sled::Config::new().path(lock.dir()).flush_every_ms(flush_every_ms);
+
+        let db = config.open()?;
+        Ok(Self { db, _lock: lock })
+    }
+}
+
+impl StorageBackend for SledBackend {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.db.get(key)?.map(|value| value.to_vec()))
+    }
+
+    fn put(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.db.insert(key, value)?;
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), Error> {
+        self.db.remove(key)?;
+        Ok(())
+    }
+
+    fn scan(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let mut pairs = vec![];
+        for pair in self.db.scan_prefix(prefix) {
+            let (key, value) = pair?;
+            let key = String::from_utf8_lossy(&key).to_string();
+            pairs.push((key, value.to_vec()));
+        }
+
+        Ok(pairs)
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn apply_batch(
+        &mut self,
+        batch: Vec<(String, Option<Vec<u8>>)>,
+    ) -> Result<(), Error> {
+        let mut sled_batch = sled::Batch::default();
+        for (key, value) in batch {
+            match value {
+                Some(value) => sled_batch.insert(key.as_str(), value),
+                None => sled_batch.remove(key.as_str()),
+            }
+        }
+
+        self.db.apply_batch(sled_batch)?;
+        Ok(())
+    }
+
+    fn keys(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let mut keys = vec![];
+        for key in self.db.scan_prefix(prefix).keys() {
+            keys.push(String::from_utf8_lossy(&key?).to_string());
+        }
+
+        Ok(keys)
+    }
+
+    fn contains(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.db.contains_key(key)?)
+    }
+
+    fn len(&self) -> Result<usize, Error> {
+        Ok(self.db.len())
+    }
+}
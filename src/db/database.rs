@@ -1,29 +1,50 @@
 use super::*;
 
+/// Key prefix of the internal keys that aren't collection names.
+const INTERNAL_PREFIX: &str = "\0";
+/// Key prefix of the collections staged for promotion.
+const STAGED_PREFIX: &str = "\0staged:";
+/// Key prefix of the collections replaced by a promotion.
+const PREVIOUS_PREFIX: &str = "\0previous:";
+
 /// The database storing vector collections.
 #[pyclass(module = "sahomedb.database")]
 pub struct Database {
-    collections: Db,
+    collections: Box<dyn StorageBackend>,
     count: usize,
+    search_pool: Option<ThreadPool>,
+}
+
+/// The write operation to apply in a database transaction.
+#[derive(Debug, Clone, Copy)]
+pub enum Operation<'a> {
+    /// Saves new or updates existing collection with the name.
+    Save(&'a str, &'a Collection),
+    /// Deletes the collection with the name.
+    Delete(&'a str),
 }
 
 #[pymethods]
 impl Database {
     /// Re-creates and opens the database at the given path.
-    /// This method will delete the database if it exists.
+    /// This method will delete the database if it exists unless
+    /// another process has it open.
     /// * `path` - Directory to store the database.
     #[staticmethod]
     pub fn new(path: &str) -> Result<Self, Error> {
-        // Remove the database dir if it exists.
-        if Path::new(path).exists() {
-            remove_dir_all(path)?;
-        }
+        // Lock the dir first so a database in use isn't removed.
+        let lock = DirLock::acquire(path)?;
+        lock.clear()?;
 
-        // Using sled::Config to prevent name collisions
-        // with collection's Config.
-        let config = sled::Config::new().path(path);
-        let collections = config.open()?;
-        Ok(Self { collections, count: 0 })
+        let collections = SledBackend::open_locked(lock, Some(500))?;
+        Self::with_backend(Box::new(collections))
+    }
+
+    /// Creates a database that keeps the collections in memory.
+    /// The collections are lost when the database is dropped.
+    #[staticmethod]
+    pub fn in_memory() -> Result<Self, Error> {
+        Self::with_backend(Box::new(MemoryBackend::new()))
     }
 
     /// Opens existing or creates new database.
@@ -31,22 +52,23 @@ impl Database {
     /// * `path` - Directory to store the database.
     #[new]
     pub fn open(path: &str) -> Result<Self, Error> {
-        let collections = sled::open(path)?;
-        let count = collections.len();
-        Ok(Self { collections, count })
+        let collections = SledBackend::open(path)?;
+        Self::with_backend(Box::new(collections))
     }
 
-    /// Gets a collection from the database.
+    /// Gets a collection from the database. Collections saved by
+    /// earlier versions are migrated and their index is rebuilt.
     /// * `name` - Name of the collection.
     pub fn get_collection(&self, name: &str) -> Result<Collection, Error> {
         let value = self.collections.get(name)?;
         match value {
-            Some(value) => Ok(bincode::deserialize(&value)?),
+            Some(value) => Collection::from_stored(&value),
             None => Err(Error::collection_not_found()),
         }
     }
 
     /// Saves new or update existing collection to the database.
+    /// Names starting with a null character are reserved.
     /// * `name` - Name of the collection.
     /// * `collection` - Vector collection to save.
     pub fn save_collection(
@@ -54,15 +76,16 @@ impl Database {
         name: &str,
         collection: &Collection,
     ) -> Result<(), Error> {
+        validate_name(name)?;
         let mut new = false;
 
         // Check if it's a new collection.
-        if !self.collections.contains_key(name)? {
+        if !self.collections.contains(name)? {
             new = true;
         }
 
-        let value = bincode::serialize(collection)?;
-        self.collections.insert(name, value)?;
+        let value = collection.to_bytes();
+        self.collections.put(name, &value)?;
 
         // If it's a new collection, update the count.
         if new {
@@ -75,11 +98,140 @@ impl Database {
     /// Deletes a collection from the database.
     /// * `name` - Collection name to delete.
     pub fn delete_collection(&mut self, name: &str) -> Result<(), Error> {
-        self.collections.remove(name)?;
+        validate_name(name)?;
+        if !self.collections.contains(name)? {
+            return Err(Error::collection_not_found());
+        }
+
+        self.collections.delete(name)?;
         self.count -= 1;
         Ok(())
     }
 
+    /// Writes the collection to a single file in the binary format
+    /// with its config, records, and indexes. The file can be imported
+    /// into another database with `import_collection`.
+    /// * `name` - Name of the collection.
+    /// * `path` - File path to write to.
+    pub fn export_collection(
+        &self,
+        name: &str,
+        path: &str,
+    ) -> Result<(), Error> {
+        self.get_collection(name)?.save_to(path)
+    }
+
+    /// Saves the collection from a file written by `export_collection`
+    /// or `Collection.save_to`. Other collections are left as is.
+    /// * `name` - Name to save the collection as.
+    /// * `path` - File path to read from.
+    pub fn import_collection(
+        &mut self,
+        name: &str,
+        path: &str,
+    ) -> Result<(), Error> {
+        let collection = Collection::load_from(path)?;
+        self.save_collection(name, &collection)
+    }
+
+    /// Searches multiple collections for the nearest neighbors and
+    /// merges the results by distance. Each result is paired with
+    /// the name of the collection it's from.
+    /// * `names` - Names of the collections to search.
+    /// * `vector` - Vector to search.
+    /// * `n` - Number of neighbors to return.
+    pub fn search_collections(
+        &self,
+        names: Vec<String>,
+        vector: &Vector,
+        n: usize,
+    ) -> Result<Vec<(String, SearchResult)>, Error> {
+        let search = |name: &String| {
+            let collection = self.get_collection(name)?;
+            let results = collection.search(vector, n)?;
+            let labeled = results.into_iter().map(|res| (name.clone(), res));
+            Ok(labeled.collect::<Vec<_>>())
+        };
+
+        let search_all = || names.par_iter().map(search).collect();
+        let results: Result<Vec<_>, Error> = match &self.search_pool {
+            Some(pool) => pool.install(search_all),
+            None => search_all(),
+        };
+
+        let mut results: Vec<_> = results?.into_iter().flatten().collect();
+
+        results.sort_by(|a, b| a.1.distance.total_cmp(&b.1.distance));
+        results.truncate(n);
+        Ok(results)
+    }
+
+    /// Saves a new version of the collection to be promoted later.
+    /// The current version keeps serving reads until the promotion.
+    /// * `name` - Name of the collection.
+    /// * `collection` - New version of the collection.
+    pub fn stage_collection(
+        &mut self,
+        name: &str,
+        collection: &Collection,
+    ) -> Result<(), Error> {
+        validate_name(name)?;
+        let value = collection.to_bytes();
+        self.collections.put(&staged_key(name), &value)
+    }
+
+    /// Replaces the collection with its staged version in one
+    /// transaction. The replaced version is kept for a rollback.
+    /// * `name` - Name of the collection.
+    pub fn promote_collection(&mut self, name: &str) -> Result<(), Error> {
+        let staged = match self.collections.get(&staged_key(name))? {
+            Some(staged) => staged,
+            None => return Err(Error::staged_collection_not_found()),
+        };
+
+        let current = self.collections.get(name)?;
+        let batch = vec![
+            (previous_key(name), current.clone()),
+            (name.to_string(), Some(staged)),
+            (staged_key(name), None),
+        ];
+
+        self.collections.apply_batch(batch)?;
+        if current.is_none() {
+            self.count += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Swaps the collection with the version replaced by the last
+    /// promotion. Rolling back again restores the promoted version.
+    /// * `name` - Name of the collection.
+    pub fn rollback_collection(&mut self, name: &str) -> Result<(), Error> {
+        let previous = match self.collections.get(&previous_key(name))? {
+            Some(previous) => previous,
+            None => return Err(Error::previous_collection_not_found()),
+        };
+
+        let current = self.collections.get(name)?;
+        let batch = vec![
+            (previous_key(name), current.clone()),
+            (name.to_string(), Some(previous)),
+        ];
+
+        self.collections.apply_batch(batch)?;
+        if current.is_none() {
+            self.count += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Ensures the saved collections are persisted to the storage.
+    pub fn flush(&self) -> Result<(), Error> {
+        self.collections.flush()
+    }
+
     /// Returns the number of collections in the database.
     pub fn len(&self) -> usize {
         self.count
@@ -94,3 +246,118 @@ impl Database {
         self.len()
     }
 }
+
+impl Database {
+    /// Applies the operations to the database in one transaction.
+    /// Either all operations are applied or none of them are.
+    /// The atomicity is provided by the storage backend. The Sled,
+    /// file, and memory backends all apply the writes atomically.
+    /// * `operations` - Save and delete operations to apply.
+    pub fn apply(&mut self, operations: &[Operation]) -> Result<(), Error> {
+        // Prepare all the writes before applying any of them
+        // so that a failing operation doesn't leave partial writes.
+        let mut batch = Vec::with_capacity(operations.len());
+        for operation in operations {
+            match operation {
+                Operation::Save(name, collection) => {
+                    validate_name(name)?;
+                    let value = collection.to_bytes();
+                    batch.push((name.to_string(), Some(value)));
+                }
+                Operation::Delete(name) => {
+                    validate_name(name)?;
+                    if !self.collections.contains(name)? {
+                        return Err(Error::collection_not_found());
+                    }
+
+                    batch.push((name.to_string(), None));
+                }
+            }
+        }
+
+        self.collections.apply_batch(batch)?;
+        self.count = count_collections(self.collections.as_ref())?;
+        Ok(())
+    }
+
+    /// Opens the database using a custom storage backend.
+    /// * `backend` - Storage to persist the collections.
+    pub fn with_backend(
+        backend: Box<dyn StorageBackend>,
+    ) -> Result<Self, Error> {
+        let count = count_collections(backend.as_ref())?;
+        Ok(Self { collections: backend, count, search_pool: None })
+    }
+
+    /// Searches the collections in `search_collections` on a dedicated
+    /// thread pool instead of the global pool shared with the builds
+    /// and the rest of the application.
+    /// * `threads` - Number of threads. 0 to use the number of CPUs.
+    ///   None to search on the global pool again.
+    pub fn set_search_threads(
+        &mut self,
+        threads: Option<usize>,
+    ) -> Result<(), Error> {
+        self.search_pool = match threads {
+            Some(threads) => Some(
+                ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .thread_name(|i| format!("sahomedb-search-{i}"))
+                    .build()?,
+            ),
+            None => None,
+        };
+
+        Ok(())
+    }
+
+    /// Migrates the data of the collection records in one transaction.
+    /// The replaced version is kept so `rollback_collection` undoes it.
+    /// Returns the number of changed records.
+    /// * `name` - Name of the collection.
+    /// * `migrations` - Changes to the data fields like renames.
+    pub fn migrate_collection(
+        &mut self,
+        name: &str,
+        migrations: &[Migration],
+    ) -> Result<usize, Error> {
+        let current = match self.collections.get(name)? {
+            Some(current) => current,
+            None => return Err(Error::collection_not_found()),
+        };
+
+        let mut collection = Collection::from_stored(&current)?;
+        let changed = collection.migrate_data(migrations)?;
+        let batch = vec![
+            (previous_key(name), Some(current)),
+            (name.to_string(), Some(collection.to_bytes())),
+        ];
+
+        self.collections.apply_batch(batch)?;
+        Ok(changed)
+    }
+}
+
+/// Returns an error if the name is reserved for the internal keys.
+fn validate_name(name: &str) -> Result<(), Error> {
+    match name.starts_with(INTERNAL_PREFIX) {
+        true => Err(Error::reserved_collection_name()),
+        false => Ok(()),
+    }
+}
+
+/// Returns the storage key of the staged collection version.
+fn staged_key(name: &str) -> String {
+    format!("{STAGED_PREFIX}{name}")
+}
+
+/// Returns the storage key of the replaced collection version.
+fn previous_key(name: &str) -> String {
+    format!("{PREVIOUS_PREFIX}{name}")
+}
+
+/// Returns the number of collections excluding the internal keys.
+fn count_collections(backend: &dyn StorageBackend) -> Result<usize, Error> {
+    let internal = backend.keys(INTERNAL_PREFIX)?.len();
+    Ok(backend.len()? - internal)
+}
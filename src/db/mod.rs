@@ -1,9 +1,20 @@
+/// Storage backends to persist the collections.
+pub mod backend;
 /// The vector database storing collections.
 pub mod database;
 
 use crate::collection::*;
 use crate::func::err::Error;
+use crate::migration::Migration;
+use crate::vector::*;
+use backend::*;
+use fs2::{lock_contended_error, FileExt};
 use pyo3::prelude::*;
+use rayon::iter::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use sled::Db;
-use std::fs::remove_dir_all;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::fs::*;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
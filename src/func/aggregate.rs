@@ -0,0 +1,264 @@
+use super::*;
+
+/// The distinct value of a data field that can be counted.
+/// Floats, objects, and nulls aren't counted as facets.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum FacetValue {
+    /// A text value like a category or a tag.
+    Text(String),
+    /// An integer value like a year.
+    Integer(usize),
+}
+
+/// The number of records having the value in the data field.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FacetCount {
+    /// Value of the data field.
+    pub value: FacetValue,
+    /// Number of records with the value.
+    pub count: usize,
+}
+
+/// The number of records with a numeric value in the range.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistogramBucket {
+    /// Inclusive lower bound of the range.
+    pub start: f32,
+    /// Exclusive upper bound of the range.
+    pub end: f32,
+    /// Number of records with a value in the range.
+    pub count: usize,
+}
+
+/// The distribution of the vectors in a collection.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VectorStats {
+    /// Number of vectors.
+    pub count: usize,
+    /// Mean of each dimension.
+    pub mean: Vec<f32>,
+    /// Standard deviation of each dimension.
+    pub std: Vec<f32>,
+    /// Number of vectors per range of their norm. The norms are
+    /// rounded down to 1/16 of their power of two first.
+    pub norms: Vec<HistogramBucket>,
+}
+
+/// Counts the records per distinct value of the data field sorted
+/// by descending count. Each distinct value in an array field is
+/// counted once per record like the tags of an article.
+/// * `data`: Data of the records to aggregate.
+/// * `field`: Name of the data object field to count.
+pub fn facet_counts<'a>(
+    data: impl IntoIterator<Item = &'a Metadata>,
+    field: &str,
+) -> Vec<FacetCount> {
+    let mut counts: HashMap<FacetValue, usize> = HashMap::new();
+    for metadata in data {
+        let mut values: Vec<FacetValue> = match metadata.field(field) {
+            Some(Metadata::Array(arr)) => {
+                arr.iter().filter_map(facet_value).collect()
+            }
+            Some(value) => facet_value(value).into_iter().collect(),
+            None => continue,
+        };
+
+        values.sort();
+        values.dedup();
+        for value in values {
+            *counts.entry(value).or_default() += 1;
+        }
+    }
+
+    let mut facets: Vec<FacetCount> = counts
+        .into_iter()
+        .map(|(value, count)| FacetCount { value, count })
+        .collect();
+
+    facets.sort_by(|a, b| b.count.cmp(&a.count).then(a.value.cmp(&b.value)));
+    facets
+}
+
+/// Counts the records per range of the numeric data field.
+/// The buckets are aligned to multiples of the interval and sorted
+/// by their start. Buckets without records are omitted.
+/// * `data`: Data of the records to aggregate.
+/// * `field`: Name of the data object field to aggregate.
+/// * `interval`: Width of each bucket. Must be positive.
+pub fn histogram<'a>(
+    data: impl IntoIterator<Item = &'a Metadata>,
+    field: &str,
+    interval: f32,
+) -> Result<Vec<HistogramBucket>, Error> {
+    if !(interval.is_finite() && interval > 0.0) {
+        return Err(Error::invalid_interval(interval));
+    }
+
+    let values =
+        data.into_iter().filter_map(|metadata| match metadata.field(field) {
+            Some(Metadata::Integer(int)) => Some(*int as f32),
+            Some(Metadata::Float(float)) if float.is_finite() => Some(*float),
+            _ => None,
+        });
+
+    Ok(buckets(values.map(|value| (value, 1)), interval))
+}
+
+/// Number of mantissa bits kept by the norm buckets. Each power
+/// of two is split into 16 buckets so the norms are kept with
+/// a relative error of at most 1/16.
+const NORM_MANTISSA_BITS: u32 = 4;
+/// Smallest and largest norm bucket keys. The norms outside of
+/// 2^-16 and 2^16 are counted in the first or the last bucket.
+const NORM_MIN_KEY: u32 = (127 - 16) << NORM_MANTISSA_BITS;
+const NORM_MAX_KEY: u32 = ((127 + 16) << NORM_MANTISSA_BITS) - 1;
+
+/// The running sums of the vectors to summarize their distribution
+/// without walking them. The sums are kept in f64 to limit the
+/// rounding drift from removing vectors.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct VectorSums {
+    count: usize,
+    sums: Vec<f64>,
+    squares: Vec<f64>,
+    /// Number of vectors per fixed norm bucket on a log scale.
+    norms: Vec<usize>,
+}
+
+impl VectorSums {
+    /// Adds the vector to the sums.
+    /// * `vector`: Vector of the same dimension as the others.
+    pub fn add(&mut self, vector: &Vector) {
+        if self.sums.is_empty() {
+            self.sums.resize(vector.len(), 0.0);
+            self.squares.resize(vector.len(), 0.0);
+            let buckets = NORM_MAX_KEY - NORM_MIN_KEY + 1;
+            self.norms.resize(buckets as usize, 0);
+        }
+
+        for (i, x) in vector.0.iter().enumerate() {
+            self.sums[i] += *x as f64;
+            self.squares[i] += (*x as f64).powi(2);
+        }
+
+        self.count += 1;
+        self.norms[norm_bucket(vector)] += 1;
+    }
+
+    /// Removes the vector previously added from the sums.
+    /// * `vector`: Vector to remove.
+    pub fn remove(&mut self, vector: &Vector) {
+        // Start over once empty so the rounding drift doesn't linger.
+        if self.count <= 1 {
+            *self = Self::default();
+            return;
+        }
+
+        for (i, x) in vector.0.iter().enumerate() {
+            self.sums[i] -= *x as f64;
+            self.squares[i] -= (*x as f64).powi(2);
+        }
+
+        self.count -= 1;
+        let count = &mut self.norms[norm_bucket(vector)];
+        *count = count.saturating_sub(1);
+    }
+
+    /// Returns the stats of the vectors added to the sums.
+    /// * `interval`: Width of each norm bucket. Must be positive.
+    pub fn stats(&self, interval: f32) -> Result<VectorStats, Error> {
+        if !(interval.is_finite() && interval > 0.0) {
+            return Err(Error::invalid_interval(interval));
+        }
+
+        // Variance from the sums as E[x^2] - E[x]^2.
+        let len = self.count.max(1) as f64;
+        let mean: Vec<f64> = self.sums.iter().map(|sum| sum / len).collect();
+        let std = self.squares.iter().zip(mean.iter()).map(|(square, mean)| {
+            let variance = square / len - mean * mean;
+            variance.max(0.0).sqrt() as f32
+        });
+
+        // Each norm bucket is counted at its lower bound.
+        let norms = self.norms.iter().enumerate().filter(|(_, n)| **n > 0);
+        let norms = norms.map(|(i, count)| {
+            let key = i as u32 + NORM_MIN_KEY;
+            (f32::from_bits(key << (23 - NORM_MANTISSA_BITS)), *count)
+        });
+
+        Ok(VectorStats {
+            count: self.count,
+            mean: mean.iter().map(|mean| *mean as f32).collect(),
+            std: std.collect(),
+            norms: buckets(norms, interval),
+        })
+    }
+
+    /// Returns the estimated memory usage of the sums in bytes.
+    pub fn memory_usage(&self) -> usize {
+        let sums = vec_size(&self.sums) + vec_size(&self.squares);
+        sums + vec_size(&self.norms)
+    }
+}
+
+impl<'a> FromIterator<&'a Vector> for VectorSums {
+    fn from_iter<T: IntoIterator<Item = &'a Vector>>(vectors: T) -> Self {
+        let mut sums = Self::default();
+        vectors.into_iter().for_each(|vector| sums.add(vector));
+        sums
+    }
+}
+
+/// Summarizes the distribution of the vectors like their norms and
+/// the mean of each dimension. Comparing the stats over time helps
+/// to detect a drifting embedding model or mixed models.
+/// * `vectors`: Vectors of the same dimension.
+/// * `interval`: Width of each norm bucket. Must be positive.
+pub fn vector_stats<'a>(
+    vectors: impl IntoIterator<Item = &'a Vector>,
+    interval: f32,
+) -> Result<VectorStats, Error> {
+    vectors.into_iter().collect::<VectorSums>().stats(interval)
+}
+
+/// Returns the Euclidean norm of the vector.
+fn norm(vector: &Vector) -> f32 {
+    vector.0.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Returns the index of the fixed bucket of the vector norm.
+/// The key is the exponent and top mantissa bits of the norm.
+fn norm_bucket(vector: &Vector) -> usize {
+    let key = norm(vector).to_bits() >> (23 - NORM_MANTISSA_BITS);
+    (key.clamp(NORM_MIN_KEY, NORM_MAX_KEY) - NORM_MIN_KEY) as usize
+}
+
+/// Counts the values per bucket aligned to multiples of the interval.
+/// Each value is paired with the number of times it occurs.
+fn buckets(
+    values: impl Iterator<Item = (f32, usize)>,
+    interval: f32,
+) -> Vec<HistogramBucket> {
+    let mut counts: BTreeMap<i64, usize> = BTreeMap::new();
+    for (value, count) in values {
+        let bucket = (value / interval).floor() as i64;
+        *counts.entry(bucket).or_default() += count;
+    }
+
+    let to_bucket = |(bucket, count): (i64, usize)| {
+        let start = bucket as f32 * interval;
+        HistogramBucket { start, end: start + interval, count }
+    };
+
+    counts.into_iter().map(to_bucket).collect()
+}
+
+/// Returns the countable facet value of the metadata.
+fn facet_value(metadata: &Metadata) -> Option<FacetValue> {
+    match metadata {
+        Metadata::Text(text) => Some(FacetValue::Text(text.clone())),
+        Metadata::Integer(int) => Some(FacetValue::Integer(*int)),
+        _ => None,
+    }
+}
@@ -0,0 +1,488 @@
+use super::*;
+
+/// Magic bytes at the start of the binary collection format.
+pub const MAGIC: &[u8; 4] = b"SAHO";
+/// Current version of the binary collection format. Bump it when
+/// the layout of the existing data changes. New optional data goes
+/// into a new section instead so older files stay readable.
+pub const VERSION: u8 = 3;
+/// Oldest version of the binary collection format still readable.
+pub const MIN_VERSION: u8 = 2;
+/// Max nesting of the metadata arrays and objects when decoding.
+const MAX_DEPTH: usize = 128;
+
+/// Tags of the optional sections after the index. Sections are
+/// written in ascending tag order and only when they have data.
+pub mod section {
+    pub const ACCESS: u8 = 1;
+    pub const TEXT_INDEX: u8 = 2;
+    pub const GEO_INDEX: u8 = 3;
+    pub const TRASH: u8 = 4;
+    pub const TRANSFORMS: u8 = 5;
+    pub const HISTORY: u8 = 6;
+    pub const MODEL: u8 = 7;
+}
+
+/// Encodes values into the compact binary format.
+/// Numbers are little-endian and lengths are varints.
+#[derive(Debug, Default)]
+pub struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    /// Creates a writer starting with the format header.
+    pub fn new() -> Self {
+        let mut writer = Self::default();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u8(VERSION);
+        writer
+    }
+
+    /// Returns the encoded bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Writes an optional section prefixed by its tag and length
+    /// so that readers can tell which sections the data has.
+    /// * `tag`: Tag of the section from the `section` module.
+    /// * `write`: Function writing the section content.
+    pub fn section(&mut self, tag: u8, write: impl FnOnce(&mut Writer)) {
+        let mut section = Writer::default();
+        write(&mut section);
+        self.u8(tag);
+        self.usize(section.bytes.len());
+        self.bytes.extend_from_slice(&section.bytes);
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Writes the number using 7 bits per byte so small numbers
+    /// like lengths and counts take a single byte.
+    pub fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.u8(value as u8 | 0x80);
+            value >>= 7;
+        }
+
+        self.u8(value as u8);
+    }
+
+    pub fn usize(&mut self, value: usize) {
+        self.varint(value as u64);
+    }
+
+    pub fn option_usize(&mut self, value: Option<usize>) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            self.usize(value);
+        }
+    }
+
+    pub fn str(&mut self, value: &str) {
+        self.usize(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    /// Writes the links of the node up to the first invalid one.
+    pub fn links(&mut self, links: &[VectorID]) {
+        let len = links.iter().take_while(|id| id.is_valid()).count();
+        self.usize(len);
+        links[..len].iter().for_each(|id| self.u32(id.0));
+    }
+
+    pub fn metadata(&mut self, value: &Metadata) {
+        match value {
+            Metadata::Text(text) => {
+                self.u8(0);
+                self.str(text);
+            }
+            Metadata::Integer(int) => {
+                self.u8(1);
+                self.usize(*int);
+            }
+            Metadata::Float(float) => {
+                self.u8(2);
+                self.f32(*float);
+            }
+            Metadata::Array(arr) => {
+                self.u8(3);
+                self.usize(arr.len());
+                arr.iter().for_each(|item| self.metadata(item));
+            }
+            Metadata::Object(obj) => {
+                self.u8(4);
+                self.usize(obj.len());
+                for (key, value) in obj.iter() {
+                    self.str(key);
+                    self.metadata(value);
+                }
+            }
+            Metadata::Null => self.u8(5),
+        }
+    }
+
+    pub fn vector(&mut self, vector: &Vector) {
+        self.usize(vector.len());
+        vector.0.iter().for_each(|x| self.f32(*x));
+    }
+
+    pub fn transform(&mut self, transform: &Transform) {
+        match transform {
+            Transform::Normalize => self.u8(0),
+            Transform::Center(mean) => {
+                self.u8(1);
+                self.vector(mean);
+            }
+            Transform::Rotate(matrix) => {
+                self.u8(2);
+                self.usize(matrix.len());
+                matrix.iter().for_each(|row| self.vector(row));
+            }
+        }
+    }
+
+    pub fn config(&mut self, config: &Config) {
+        // Destructure so a new config field can't be missed here.
+        let Config {
+            ef_construction,
+            ef_search,
+            ml,
+            max_memory,
+            max_records,
+            eviction,
+            repair_every,
+            heuristic,
+            dedup,
+            dedup_distance,
+            trash_retention,
+            history_retention,
+            default_n,
+            max_n,
+        } = *config;
+
+        self.usize(ef_construction);
+        self.usize(ef_search);
+        self.f32(ml);
+        self.option_usize(max_memory);
+        self.option_usize(max_records);
+
+        let eviction = match eviction {
+            None => 0,
+            Some(EvictionPolicy::Lru) => 1,
+            Some(EvictionPolicy::Lfu) => 2,
+        };
+
+        self.u8(eviction);
+        self.option_usize(repair_every);
+
+        self.bool(heuristic.is_some());
+        if let Some(heuristic) = heuristic {
+            self.bool(heuristic.extend_candidates);
+            self.bool(heuristic.keep_pruned);
+        }
+
+        let dedup = match dedup {
+            None => 0,
+            Some(DedupPolicy::Skip) => 1,
+            Some(DedupPolicy::Merge) => 2,
+        };
+
+        self.u8(dedup);
+        self.f32(dedup_distance);
+        self.option_usize(trash_retention);
+        self.option_usize(history_retention);
+        self.usize(default_n);
+        self.option_usize(max_n);
+    }
+}
+
+/// Decodes values written by the `Writer`.
+#[derive(Debug)]
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    version: u8,
+    // Tag of the last section read to keep them in order.
+    section: u8,
+}
+
+impl<'a> Reader<'a> {
+    /// Creates a reader after validating the format header.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        let mut reader = Self { bytes, position: 0, version: 0, section: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(Error::invalid_format("Unknown file type."));
+        }
+
+        reader.version = reader.u8()?;
+        if !(MIN_VERSION..=VERSION).contains(&reader.version) {
+            let version = reader.version;
+            let detail = format!("Unsupported version: {version}.");
+            return Err(Error::invalid_format(&detail));
+        }
+
+        Ok(reader)
+    }
+
+    /// Returns the format version from the header of the data.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Reads the next optional section. Returns its tag and a reader
+    /// over its content or None if there are no more sections.
+    pub fn section(&mut self) -> Result<Option<(u8, Reader<'a>)>, Error> {
+        if self.is_done() {
+            return Ok(None);
+        }
+
+        let tag = self.u8()?;
+        if tag <= self.section {
+            return Err(Error::invalid_format("Invalid section order."));
+        }
+
+        self.section = tag;
+        let len = self.length()?;
+        let bytes = self.take(len)?;
+        let version = self.version;
+        Ok(Some((tag, Reader { bytes, position: 0, version, section: 0 })))
+    }
+
+    /// Returns true if all bytes have been read.
+    pub fn is_done(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self.position.saturating_add(len);
+        let bytes = match self.bytes.get(self.position..end) {
+            Some(bytes) => bytes,
+            None => return Err(Error::invalid_format("Unexpected end.")),
+        };
+
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, Error> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::invalid_format("Invalid boolean.")),
+        }
+    }
+
+    pub fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    pub fn varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return Ok(value);
+            }
+        }
+
+        Err(Error::invalid_format("Invalid varint."))
+    }
+
+    pub fn usize(&mut self) -> Result<usize, Error> {
+        Ok(self.varint()? as usize)
+    }
+
+    /// Reads a length and ensures the remaining bytes can hold
+    /// that many items to avoid huge allocations on bad input.
+    pub fn length(&mut self) -> Result<usize, Error> {
+        let len = self.usize()?;
+        if len > self.bytes.len() - self.position {
+            return Err(Error::invalid_format("Invalid length."));
+        }
+
+        Ok(len)
+    }
+
+    pub fn option_usize(&mut self) -> Result<Option<usize>, Error> {
+        match self.bool()? {
+            true => Ok(Some(self.usize()?)),
+            false => Ok(None),
+        }
+    }
+
+    pub fn string(&mut self) -> Result<String, Error> {
+        let len = self.length()?;
+        match String::from_utf8(self.take(len)?.to_vec()) {
+            Ok(string) => Ok(string),
+            Err(_) => Err(Error::invalid_format("Invalid text.")),
+        }
+    }
+
+    /// Reads the node links into the fixed-size node array.
+    pub fn links(&mut self, node: &mut [VectorID]) -> Result<(), Error> {
+        let len = self.length()?;
+        if len > node.len() {
+            return Err(Error::invalid_format("Too many links."));
+        }
+
+        for link in node.iter_mut().take(len) {
+            *link = VectorID(self.u32()?);
+        }
+
+        Ok(())
+    }
+
+    pub fn metadata(&mut self) -> Result<Metadata, Error> {
+        self.nested_metadata(0)
+    }
+
+    /// Reads the metadata limiting the nesting so that corrupted
+    /// data can't overflow the stack.
+    /// * `depth`: Number of arrays and objects containing it.
+    fn nested_metadata(&mut self, depth: usize) -> Result<Metadata, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::invalid_format("Metadata is nested too deep."));
+        }
+
+        let metadata = match self.u8()? {
+            0 => Metadata::Text(self.string()?),
+            1 => Metadata::Integer(self.usize()?),
+            2 => Metadata::Float(self.f32()?),
+            3 => {
+                let len = self.length()?;
+                let items = (0..len).map(|_| self.nested_metadata(depth + 1));
+                Metadata::Array(items.collect::<Result<_, _>>()?)
+            }
+            4 => {
+                let len = self.length()?;
+                let pairs = (0..len).map(|_| {
+                    let key = self.string()?;
+                    Ok((key, self.nested_metadata(depth + 1)?))
+                });
+
+                Metadata::Object(pairs.collect::<Result<_, Error>>()?)
+            }
+            5 => Metadata::Null,
+            _ => return Err(Error::invalid_format("Invalid metadata.")),
+        };
+
+        Ok(metadata)
+    }
+
+    pub fn vector(&mut self) -> Result<Vector, Error> {
+        let len = self.length()?;
+        let values = (0..len).map(|_| self.f32());
+        Ok(Vector(values.collect::<Result<_, _>>()?))
+    }
+
+    pub fn transform(&mut self) -> Result<Transform, Error> {
+        let transform = match self.u8()? {
+            0 => Transform::Normalize,
+            1 => Transform::Center(self.vector()?),
+            2 => {
+                let len = self.length()?;
+                let rows = (0..len).map(|_| self.vector());
+                Transform::Rotate(rows.collect::<Result<_, _>>()?)
+            }
+            _ => return Err(Error::invalid_format("Invalid transform.")),
+        };
+
+        Ok(transform)
+    }
+
+    pub fn config(&mut self) -> Result<Config, Error> {
+        let ef_construction = self.usize()?;
+        let ef_search = self.usize()?;
+        let ml = self.f32()?;
+        let max_memory = self.option_usize()?;
+        let max_records = self.option_usize()?;
+
+        let eviction = match self.u8()? {
+            0 => None,
+            1 => Some(EvictionPolicy::Lru),
+            2 => Some(EvictionPolicy::Lfu),
+            _ => return Err(Error::invalid_format("Invalid eviction.")),
+        };
+
+        let repair_every = self.option_usize()?;
+        let heuristic = match self.bool()? {
+            true => Some(Heuristic::new(self.bool()?, self.bool()?)),
+            false => None,
+        };
+
+        let dedup = match self.u8()? {
+            0 => None,
+            1 => Some(DedupPolicy::Skip),
+            2 => Some(DedupPolicy::Merge),
+            _ => return Err(Error::invalid_format("Invalid dedup.")),
+        };
+
+        let dedup_distance = self.f32()?;
+        let trash_retention = self.option_usize()?;
+
+        // Version 2 doesn't have the history retention.
+        let history_retention = match self.version {
+            2 => None,
+            _ => self.option_usize()?,
+        };
+
+        let default_n = self.usize()?;
+        let max_n = self.option_usize()?;
+
+        Ok(Config {
+            ef_construction,
+            ef_search,
+            ml,
+            max_memory,
+            max_records,
+            eviction,
+            repair_every,
+            heuristic,
+            dedup,
+            dedup_distance,
+            trash_retention,
+            history_retention,
+            default_n,
+            max_n,
+        })
+    }
+}
@@ -1,5 +1,15 @@
 use super::*;
 
+/// The number of records sampled to estimate the filter selectivity.
+const PLAN_SAMPLE: usize = 1000;
+/// The max estimated matches to compare directly when planning.
+const PLAN_EXACT_LIMIT: usize = 2000;
+/// The max number of slots an upsert can skip past the last slot.
+const MAX_ID_GAP: usize = 65536;
+/// The FNV-1a parameters to hash the vectors of a build checkpoint.
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
 /// The collection HNSW index configuration.
 #[pyclass(module = "sahomedb.collection")]
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
@@ -13,6 +23,46 @@ pub struct Config {
     /// Layer multiplier. The optimal value is `1/ln(M)`.
     #[pyo3(get, set)]
     pub ml: f32,
+    /// Memory limit in bytes after which inserts are rejected.
+    #[pyo3(get, set)]
+    pub max_memory: Option<usize>,
+    /// Max number of records after which inserts are rejected.
+    #[pyo3(get, set)]
+    pub max_records: Option<usize>,
+    /// Policy to evict records instead of rejecting inserts
+    /// when the memory or record limit is reached.
+    #[pyo3(get, set)]
+    pub eviction: Option<EvictionPolicy>,
+    /// Number of deletes after which the index is repaired
+    /// automatically. None to only repair it on demand.
+    #[pyo3(get, set)]
+    pub repair_every: Option<usize>,
+    /// Parameters of the heuristic neighbor selection.
+    /// None to select the nearest candidates as neighbors.
+    #[pyo3(get, set)]
+    pub heuristic: Option<Heuristic>,
+    /// Policy for inserts of vectors already in the collection.
+    /// None to insert duplicate vectors as new records.
+    #[pyo3(get, set)]
+    pub dedup: Option<DedupPolicy>,
+    /// Max distance for a vector to be considered a duplicate.
+    #[pyo3(get, set)]
+    pub dedup_distance: f32,
+    /// Seconds to keep the deleted records restorable in the trash.
+    /// None to delete the records permanently right away.
+    #[pyo3(get, set)]
+    pub trash_retention: Option<usize>,
+    /// Seconds to keep the past versions of the records readable.
+    /// None to keep them until the versioning is disabled.
+    #[pyo3(get, set)]
+    pub history_retention: Option<usize>,
+    /// Number of results to return if a search doesn't specify it.
+    #[pyo3(get, set)]
+    pub default_n: usize,
+    /// Max number of results a search can request.
+    /// None to allow any number of results.
+    #[pyo3(get, set)]
+    pub max_n: Option<usize>,
 }
 
 // Any modifications to this methods should be reflected in:
@@ -23,7 +73,7 @@ impl Config {
     /// Creates a new collection config with the given parameters.
     #[new]
     pub fn new(ef_construction: usize, ef_search: usize, ml: f32) -> Self {
-        Self { ef_construction, ef_search, ml }
+        Self { ef_construction, ef_search, ml, ..Default::default() }
     }
 
     #[staticmethod]
@@ -31,6 +81,27 @@ impl Config {
         Self::default()
     }
 
+    /// Validates the ranges of the index parameters and the limits.
+    /// Collections validate the config when built or decoded.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.ef_construction == 0 || self.ef_search == 0 {
+            let detail = "The ef parameters must be at least 1.";
+            return Err(Error::invalid_config(detail));
+        }
+
+        if !(0.0..1.0).contains(&self.ml) {
+            let detail = "The ml must be at least 0 and less than 1.";
+            return Err(Error::invalid_config(detail));
+        }
+
+        if !(self.dedup_distance >= 0.0 && self.dedup_distance.is_finite()) {
+            let detail = "The dedup distance must be finite and not negative.";
+            return Err(Error::invalid_config(detail));
+        }
+
+        Ok(())
+    }
+
     fn __repr__(&self) -> String {
         format!("{:?}", self)
     }
@@ -41,11 +112,202 @@ impl Default for Config {
     /// * `ef_construction`: 40
     /// * `ef_search`: 15
     /// * `ml`: 0.3
+    /// * `max_memory`: None
+    /// * `max_records`: None
+    /// * `eviction`: None
+    /// * `repair_every`: None
+    /// * `heuristic`: None
+    /// * `dedup`: None
+    /// * `dedup_distance`: 0.0
+    /// * `trash_retention`: None
+    /// * `history_retention`: None
+    /// * `default_n`: 10
+    /// * `max_n`: None
+    fn default() -> Self {
+        Self {
+            ef_construction: 40,
+            ef_search: 15,
+            ml: 0.3,
+            max_memory: None,
+            max_records: None,
+            eviction: None,
+            repair_every: None,
+            heuristic: None,
+            dedup: None,
+            dedup_distance: 0.0,
+            trash_retention: None,
+            history_retention: None,
+            default_n: 10,
+            max_n: None,
+        }
+    }
+}
+
+/// The parameters of the heuristic neighbor selection from the
+/// HNSW paper. Candidates closer to an already selected neighbor
+/// than to the inserted vector are skipped so the neighbors spread
+/// in more directions. This improves recall on clustered data.
+#[pyclass(module = "sahomedb.collection")]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Heuristic {
+    /// Considers the neighbors of the candidates as candidates.
+    #[pyo3(get, set)]
+    pub extend_candidates: bool,
+    /// Fills the remaining links with the skipped candidates.
+    #[pyo3(get, set)]
+    pub keep_pruned: bool,
+}
+
+// Any modifications to this methods should be reflected in:
+// - py/tests/test_collection.py
+// - py/sahomedb/collection.pyi
+#[pymethods]
+impl Heuristic {
+    /// Creates new heuristic neighbor selection parameters.
+    #[new]
+    pub fn new(extend_candidates: bool, keep_pruned: bool) -> Self {
+        Self { extend_candidates, keep_pruned }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl Default for Heuristic {
+    /// Default heuristic neighbor selection parameters.
+    /// * `extend_candidates`: false
+    /// * `keep_pruned`: true
     fn default() -> Self {
-        Self { ef_construction: 40, ef_search: 15, ml: 0.3 }
+        Self { extend_candidates: false, keep_pruned: true }
+    }
+}
+
+/// The score to report for the search results along with the
+/// distance. The distance is always the Euclidean distance.
+#[pyclass(module = "sahomedb.collection")]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ScoreMode {
+    /// Euclidean distance where lower is better.
+    Distance,
+    /// Similarity from 0 to 1 where 1 is the same vector.
+    /// Computed as `1 / (1 + distance)`.
+    Similarity,
+    /// Cosine similarity from -1 to 1 where higher is better.
+    /// It's 0 if either vector has no magnitude.
+    Cosine,
+}
+
+impl ScoreMode {
+    /// Returns the score of the vector for the query.
+    /// * `query`: Vector searched for.
+    /// * `vector`: Vector of the result.
+    /// * `distance`: Distance between the vectors.
+    pub fn score(&self, query: &Vector, vector: &Vector, distance: f32) -> f32 {
+        match self {
+            ScoreMode::Distance => distance,
+            ScoreMode::Similarity => 1.0 / (1.0 + distance),
+            ScoreMode::Cosine => {
+                let pairs = query.0.iter().zip(vector.0.iter());
+                let dot: f32 = pairs.map(|(a, b)| a * b).sum();
+                let norm = |v: &Vector| v.0.iter().map(|x| x * x).sum::<f32>();
+                let norms = (norm(query) * norm(vector)).sqrt();
+                match norms > 0.0 {
+                    true => dot / norms,
+                    false => 0.0,
+                }
+            }
+        }
+    }
+}
+
+/// The policy to choose which record to evict from the collection.
+#[pyclass(module = "sahomedb.collection")]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum EvictionPolicy {
+    /// Evicts the least recently used record.
+    Lru,
+    /// Evicts the least frequently used record.
+    Lfu,
+}
+
+/// The policy for inserting a vector already in the collection.
+/// Duplicates are found with the index search so a duplicate
+/// might rarely be missed like any other nearest neighbor.
+#[pyclass(module = "sahomedb.collection")]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum DedupPolicy {
+    /// Skips the insert and returns the existing vector ID.
+    Skip,
+    /// Merges the new data into the existing record and returns its
+    /// vector ID. Object fields are added or replaced while other
+    /// data types are replaced entirely.
+    Merge,
+}
+
+/// The policy for building from records with a vector dimension
+/// different from the declared dimension of the collection.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum DimensionPolicy {
+    /// Fails the build on the first mismatched record.
+    Reject,
+    /// Builds the collection without the mismatched records.
+    Skip,
+    /// Pads shorter vectors with zeros and truncates longer vectors
+    /// to the declared dimension.
+    Coerce,
+}
+
+/// The indices of the records affected by the dimension policy.
+/// The indices refer to the records given to the build.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DimensionReport {
+    /// Records left out of the collection.
+    pub skipped: Vec<usize>,
+    /// Records whose vector was padded with zeros.
+    pub padded: Vec<usize>,
+    /// Records whose vector was truncated.
+    pub truncated: Vec<usize>,
+}
+
+impl DimensionReport {
+    /// Returns true if no record was affected.
+    pub fn is_empty(&self) -> bool {
+        let Self { skipped, padded, truncated } = self;
+        skipped.is_empty() && padded.is_empty() && truncated.is_empty()
+    }
+
+    /// Returns the vector ID assigned to the record at the index
+    /// since skipped records shift the IDs of the records after them.
+    /// None if the record was skipped.
+    /// * `index`: Index of the record given to the build.
+    pub fn vector_id(&self, index: usize) -> Option<VectorID> {
+        match self.skipped.binary_search(&index) {
+            Ok(_) => None,
+            Err(skipped) => Some((index - skipped).into()),
+        }
     }
 }
 
+/// The resource limits of building a collection index.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct BuildLimits {
+    /// Number of threads. 0 to use the number of CPUs.
+    pub max_threads: usize,
+    /// Max memory in bytes estimated from the vectors and the base
+    /// layer before the build starts. None for no limit.
+    pub max_memory: Option<usize>,
+    /// Number of records inserted between the pauses.
+    /// 0 to insert all records of a layer without pausing.
+    pub chunk_size: usize,
+    /// Pause between the chunks to free the CPUs for other work.
+    pub pause: Duration,
+    /// Runs the build threads at the lowest scheduling priority so
+    /// threads serving queries are scheduled first. Only Linux sets
+    /// the priority per thread so it's ignored on other platforms.
+    pub low_priority: bool,
+}
+
 /// The collection of vector records with HNSW indexing.
 #[pyclass(module = "sahomedb.collection")]
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -57,9 +319,23 @@ pub struct Collection {
     data: HashMap<VectorID, Metadata>,
     vectors: HashMap<VectorID, Vector>,
     slots: Vec<VectorID>,
+    free_slots: Vec<VectorID>,
     base_layer: Vec<BaseNode>,
-    upper_layers: Vec<Vec<UpperNode>>,
+    upper_layers: Vec<UpperLayer>,
+    entry_point: VectorID,
+    access: AccessTracker,
+    text_index: Option<TextIndex>,
+    geo_index: Option<GeoIndex>,
+    trash: Vec<TrashedRecord>,
+    transforms: Vec<Transform>,
+    history: Option<RecordHistory>,
+    model: Option<String>,
+    vector_sums: VectorSums,
     // Utility fields.
+    read_only: bool,
+    level_seed: u64,
+    deletes_since_repair: usize,
+    heap_size: usize,
     count: usize,
     dimension: usize,
 }
@@ -78,18 +354,11 @@ impl Index<&VectorID> for Collection {
 #[pymethods]
 impl Collection {
     /// Creates an empty collection with the given configuration.
+    /// Returns an error if the configuration is invalid.
     #[new]
-    pub fn new(config: &Config) -> Self {
-        Self {
-            config: *config,
-            count: 0,
-            dimension: 0,
-            data: HashMap::new(),
-            vectors: HashMap::new(),
-            slots: vec![],
-            base_layer: vec![],
-            upper_layers: vec![],
-        }
+    pub fn try_new(config: &Config) -> Result<Self, Error> {
+        config.validate()?;
+        Ok(Self::new(config))
     }
 
     #[staticmethod]
@@ -101,64 +370,119 @@ impl Collection {
     }
 
     /// Inserts a vector record into the collection.
+    /// Returns the vector ID assigned to the record.
     /// * `record`: Vector record to insert.
-    pub fn insert(&mut self, record: &Record) -> Result<(), Error> {
-        // Ensure the number of records is within the limit.
-        if self.slots.len() == u32::MAX as usize {
-            return Err(Error::collection_limit());
-        }
-
-        // Ensure the vector dimension matches the collection config.
-        // If it's the first record, set the dimension.
-        if self.vectors.is_empty() && self.dimension == 0 {
-            self.dimension = record.vector.len();
-        } else if record.vector.len() != self.dimension {
-            let len = record.vector.len();
-            let err = Error::invalid_dimension(len, self.dimension);
-            return Err(err);
-        }
-
-        // Create a new vector ID using the next available slot.
-        let id: VectorID = self.slots.len().into();
+    pub fn insert(&mut self, record: &Record) -> Result<VectorID, Error> {
+        let record = self.transform_record(record)?;
+        self.insert_transformed(&record, None)
+    }
 
-        // Insert the new vector and data.
-        self.vectors.insert(id, record.vector.clone());
-        self.data.insert(id, record.data.clone());
+    /// Updates the record with the vector ID if it exists or inserts
+    /// it with the vector ID otherwise. Returns true if the record was
+    /// created. The dedup policy still applies so an insert of a
+    /// duplicate vector returns an error without creating the record.
+    /// The vector ID can skip at most 65536 slots past the last one.
+    /// * `id`: Vector ID of the record.
+    /// * `record`: Vector record to update or insert.
+    pub fn upsert(
+        &mut self,
+        id: &VectorID,
+        record: &Record,
+    ) -> Result<bool, Error> {
+        self.ensure_writable()?;
 
-        // Add new vector id to the slots.
-        self.slots.push(id);
+        if self.contains(id) {
+            self.update(id, record)?;
+            return Ok(false);
+        }
 
-        // Update the collection count.
-        self.count += 1;
+        if !id.is_valid() {
+            return Err(Error::collection_limit());
+        }
 
-        // This operation is last because it depends on
-        // the updated vectors data.
-        self.insert_to_layers(&id);
+        // Limit the slots allocated for the skipped vector IDs.
+        let max = self.slots.len() + MAX_ID_GAP;
+        if id.0 as usize > max {
+            return Err(Error::invalid_vector_id(id.0, max));
+        }
 
-        Ok(())
+        let record = self.transform_record(record)?;
+        self.insert_transformed(&record, Some(id))?;
+        Ok(true)
     }
 
-    /// Deletes a vector record from the collection.
+    /// Deletes a vector record from the collection. If the trash is
+    /// enabled in the config, the record can be restored until its
+    /// retention ends.
     /// * `id`: Vector ID to delete.
     pub fn delete(&mut self, id: &VectorID) -> Result<(), Error> {
+        self.ensure_writable()?;
+
         // Ensure the vector ID exists in the collection.
         if !self.contains(id) {
             return Err(Error::record_not_found());
         }
 
-        self.delete_from_layers(id);
+        if self.config.trash_retention.is_some() {
+            let record = Record::new(&self.vectors[id], &self.data[id]);
+            let deleted_at = unix_time();
+            self.heap_size += record_size(&record.vector, &record.data);
+            self.trash.push(TrashedRecord { id: *id, record, deleted_at });
+            self.purge_trash();
+        }
 
-        // Update the collection data.
-        self.vectors.remove(id);
-        self.data.remove(id);
+        self.remove(id);
+        Ok(())
+    }
 
-        // Make the slot invalid so it won't be used again.
-        self.slots[id.0 as usize] = INVALID;
+    /// Returns the deleted records in the trash by the vector ID
+    /// they had before the deletion. If a reused ID was deleted
+    /// again, the latest deleted record is returned.
+    pub fn trash(&self) -> HashMap<VectorID, Record> {
+        let retention = self.config.trash_retention;
+        let trash = self.trash.iter().filter(|t| !t.is_expired(retention));
+        trash.map(|t| (t.id, t.record.clone())).collect()
+    }
 
-        // Update the collection count.
-        self.count -= 1;
+    /// Restores the latest deleted record with the vector ID from
+    /// the trash by inserting it again. Returns the new vector ID
+    /// which may differ from the one before the deletion.
+    /// * `id`: Vector ID of the record before the deletion.
+    pub fn restore(&mut self, id: &VectorID) -> Result<VectorID, Error> {
+        self.ensure_writable()?;
+        self.purge_trash();
+
+        let index = match self.trash.iter().rposition(|t| t.id == *id) {
+            Some(index) => index,
+            None => return Err(Error::trashed_record_not_found()),
+        };
 
-        Ok(())
+        // The trashed vector is already transformed.
+        let record = self.trash[index].record.clone();
+        let new_id = self.insert_transformed(&record, None)?;
+        let record = self.trash.remove(index).record;
+        self.heap_size -= record_size(&record.vector, &record.data);
+        Ok(new_id)
+    }
+
+    /// Permanently deletes the records kept in the trash longer than
+    /// the retention. If the trash is disabled, all of them are
+    /// deleted. Returns the number of purged records.
+    pub fn purge_trash(&mut self) -> usize {
+        let len = self.trash.len();
+        let retention = self.config.trash_retention;
+        let mut purged_size = 0;
+        self.trash.retain(|t| {
+            let expired = t.is_expired(retention);
+            if expired {
+                purged_size += record_size(&t.record.vector, &t.record.data);
+            }
+
+            !expired
+        });
+
+        self.heap_size -= purged_size;
+        len - self.trash.len()
     }
 
     /// Returns vector records in the collection as a HashMap.
@@ -186,6 +510,7 @@ impl Collection {
             return Err(Error::record_not_found());
         }
 
+        self.touch(id);
         let vector = self.vectors[id].clone();
         let data = self.data[id].clone();
         Ok(Record::new(&vector, &data))
@@ -199,76 +524,244 @@ impl Collection {
         id: &VectorID,
         record: &Record,
     ) -> Result<(), Error> {
+        self.ensure_writable()?;
+
         if !self.contains(id) {
             return Err(Error::record_not_found());
         }
 
         // Validate the new vector dimension.
+        let record = &self.transform_record(record)?;
         self.validate_dimension(&record.vector)?;
 
         // Remove the old vector from the index layers.
         self.delete_from_layers(id);
+        self.retire_version(id);
 
         // Insert the updated vector and data.
-        self.vectors.insert(*id, record.vector.clone());
-        self.data.insert(*id, record.data.clone());
+        self.set_record(id, record.vector.clone(), record.data.clone());
+        self.index_data(id);
         self.insert_to_layers(id);
+        self.touch(id);
+        self.write_version(id);
 
         Ok(())
     }
 
-    /// Searches the collection for the nearest neighbors.
+    /// Re-links the records that became unreachable in the index
+    /// after deletions by re-running their neighbor selection.
+    /// This restores the recall without rebuilding the collection.
+    /// Returns the number of re-linked records.
+    pub fn repair(&mut self) -> usize {
+        if self.read_only {
+            return 0;
+        }
+
+        self.deletes_since_repair = 0;
+        let unreachable = self.unreachable_records();
+        if !unreachable.is_empty() {
+            self.relink(&unreachable);
+        }
+
+        unreachable.len()
+    }
+
+    /// Returns the records that can't be reached from the
+    /// entry point through the base layer links.
+    pub fn unreachable_records(&self) -> Vec<VectorID> {
+        if !self.entry_point.is_valid() {
+            return vec![];
+        }
+
+        let mut visited = Visited::with_capacity(self.slots.len());
+        visited.insert(&self.entry_point);
+
+        let mut stack = vec![self.entry_point];
+        while let Some(id) = stack.pop() {
+            let links = self.base_layer[id.0 as usize].iter();
+            let links = links.take_while(|link| link.is_valid());
+            stack.extend(links.filter(|link| visited.insert(link)));
+        }
+
+        let mut ids: Vec<VectorID> = self
+            .vectors
+            .keys()
+            .filter(|id| visited.insert(id))
+            .copied()
+            .collect();
+
+        ids.sort();
+        ids
+    }
+
+    /// Saves the collection to the file in the compact binary format.
+    /// This is smaller and faster to load than the database storage.
+    /// * `path`: File path to write to.
+    pub fn save_to(&self, path: &str) -> Result<(), Error> {
+        // Write to a temporary file first so that a crash
+        // mid-write doesn't corrupt the existing file.
+        let tmp = format!("{path}.tmp");
+        write(&tmp, self.to_bytes())?;
+        rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Loads the collection saved with `save_to` from the file.
+    /// * `path`: File path to read from.
+    #[staticmethod]
+    pub fn load_from(path: &str) -> Result<Self, Error> {
+        Self::from_bytes(&read(path)?)
+    }
+
+    /// Searches the nearest neighbors like `search` but starts the
+    /// base layer search from multiple records spread across the
+    /// collection. This improves recall on clustered data where
+    /// a single entry point can get stuck in the wrong cluster.
     /// * `vector`: Vector to search.
     /// * `n`: Number of neighbors to return.
-    pub fn search(
+    /// * `num_entry_points`: Number of entry points. 1 is like `search`.
+    pub fn search_with_entry_points(
         &self,
         vector: &Vector,
         n: usize,
+        num_entry_points: usize,
     ) -> Result<Vec<SearchResult>, Error> {
-        let mut search = Search::default();
+        self.validate_n(n)?;
+        let ef = self.config.ef_search;
+        let (candidates, _) =
+            self.search_layers(vector, ef, num_entry_points, None, None)?;
+        let results = candidates.iter().take(n);
+        Ok(results.map(|candidate| self.search_result(candidate)).collect())
+    }
 
-        // Early return if the collection is empty.
-        if self.vectors.is_empty() {
-            return Ok(vec![]);
+    /// Searches the nearest neighbors like `search` but stops once
+    /// the time budget is spent and returns the nearest records found
+    /// so far. Returns the results and true if the search was cut short.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `budget_ms`: Time budget of the search in milliseconds.
+    pub fn search_with_budget(
+        &self,
+        vector: &Vector,
+        n: usize,
+        budget_ms: u64,
+    ) -> Result<(Vec<SearchResult>, bool), Error> {
+        self.validate_n(n)?;
+        let ef = self.config.ef_search;
+        let deadline = Instant::now() + Duration::from_millis(budget_ms);
+        let (candidates, partial) =
+            self.search_layers(vector, ef, 1, Some(deadline), None)?;
+        let results = candidates.iter().take(n);
+        let results = results.map(|candidate| self.search_result(candidate));
+        Ok((results.collect(), partial))
+    }
+
+    #[pyo3(name = "search", signature = (vector, n = None))]
+    fn py_search(
+        &self,
+        vector: &Vector,
+        n: Option<usize>,
+    ) -> Result<Vec<SearchResult>, Error> {
+        self.search(vector, n.unwrap_or(self.config.default_n))
+    }
+
+    /// Searches the nearest neighbors like `search` and scores the
+    /// results with the score mode. The results are still ordered
+    /// by the distance from the nearest.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `mode`: How to score the results.
+    pub fn search_scored(
+        &self,
+        vector: &Vector,
+        n: usize,
+        mode: ScoreMode,
+    ) -> Result<Vec<SearchResult>, Error> {
+        let mut results = self.search(vector, n)?;
+        let query = self.transform(vector)?;
+        for result in results.iter_mut() {
+            let other = &self.vectors[&VectorID(result.id)];
+            result.score = mode.score(&query, other, result.distance);
         }
 
-        // Ensure the vector dimension matches the collection dimension.
-        self.validate_dimension(vector)?;
+        Ok(results)
+    }
 
-        // Find the first valid vector ID from the slots.
-        let slots_iter = self.slots.as_slice().into_par_iter();
-        let vector_id = match slots_iter.find_first(|id| id.is_valid()) {
-            Some(id) => id,
-            None => return Err("Unable to initiate search.".into()),
-        };
+    /// Creates a full-text index over the text in the data fields.
+    /// Data that isn't an object such as a plain text is indexed
+    /// as a whole. This replaces the existing text index.
+    /// * `fields`: Names of the data object fields to index.
+    pub fn create_text_index(&mut self, fields: Vec<String>) {
+        let mut index = TextIndex::new(&fields);
+        for (id, data) in self.data.iter() {
+            index.insert(id, data);
+        }
 
-        search.visited.resize_capacity(self.vectors.len());
-        search.push(vector_id, vector, &self.vectors);
+        self.text_index = Some(index);
+    }
 
-        for layer in LayerID(self.upper_layers.len()).descend() {
-            search.ef = if layer.is_zero() { self.config.ef_search } else { 5 };
+    /// Searches the collection for the records most relevant to
+    /// the text query using the BM25 algorithm. The distance of
+    /// the results is `1 / (1 + score)` so lower is better.
+    /// * `query`: Text to search for.
+    /// * `n`: Number of records to return.
+    pub fn text_search(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<SearchResult>, Error> {
+        self.validate_n(n)?;
+        let index = match self.text_index.as_ref() {
+            Some(index) => index,
+            None => return Err(Error::text_index_not_found()),
+        };
 
-            if layer.0 == 0 {
-                let layer = self.base_layer.as_slice();
-                search.search(layer, vector, &self.vectors, M * 2);
-            } else {
-                let layer = self.upper_layers[layer.0 - 1].as_slice();
-                search.search(layer, vector, &self.vectors, M);
-            }
+        let results = index.search(query, n);
+        Ok(self.ranked_results(results))
+    }
 
-            if !layer.is_zero() {
-                search.cull();
+    /// Searches the collection using both the vector and text query
+    /// and fuses the rankings with reciprocal rank fusion. The
+    /// distance of the results is `1 / (1 + score)` of the fused score.
+    /// * `vector`: Vector to search.
+    /// * `query`: Text to search for.
+    /// * `n`: Number of records to return.
+    pub fn hybrid_search(
+        &self,
+        vector: &Vector,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<SearchResult>, Error> {
+        self.validate_n(n)?;
+        let index = match self.text_index.as_ref() {
+            Some(index) => index,
+            None => return Err(Error::text_index_not_found()),
+        };
+
+        // Retrieve more candidates than needed from each
+        // ranking so records ranked well in both can surface.
+        let limit = max(n, self.config.ef_search);
+        let text_ids = index.search(query, limit).into_iter().map(|r| r.0);
+        let candidates = self.search_candidates(vector, limit, None)?;
+        let vector_ids = candidates.iter().map(|c| c.vector_id);
+
+        // Constant of the reciprocal rank fusion formula.
+        let k = 60.0;
+
+        let mut scores: HashMap<VectorID, f32> = HashMap::new();
+        let rankings: [Vec<VectorID>; 2] =
+            [vector_ids.take(limit).collect(), text_ids.collect()];
+        for ids in rankings {
+            for (rank, id) in ids.into_iter().enumerate() {
+                let score = 1.0 / (k + rank as f32 + 1.0);
+                *scores.entry(id).or_default() += score;
             }
         }
 
-        let map_result = |candidate: Candidate| {
-            let id = candidate.vector_id.0;
-            let distance = candidate.distance.0;
-            let data = self.data[&candidate.vector_id].clone();
-            SearchResult { id, distance, data }
-        };
-
-        Ok(search.iter().map(map_result).take(n).collect())
+        let mut results: Vec<(VectorID, f32)> = scores.into_iter().collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        results.truncate(n);
+        Ok(self.ranked_results(results))
     }
 
     /// Searches the collection for the true nearest neighbors.
@@ -279,9 +772,11 @@ impl Collection {
         vector: &Vector,
         n: usize,
     ) -> Result<Vec<SearchResult>, Error> {
+        self.validate_n(n)?;
         let mut nearest = Vec::with_capacity(self.vectors.len());
 
         // Ensure the vector dimension matches the collection dimension.
+        let vector = &self.transform(vector)?;
         self.validate_dimension(vector)?;
 
         // Calculate the distance between the query and each record.
@@ -289,12 +784,13 @@ impl Collection {
         for (id, vec) in self.vectors.iter() {
             let distance = vector.distance(vec);
             let data = self.data[id].clone();
-            let res = SearchResult { id: id.0, distance, data };
+            let res =
+                SearchResult { id: id.0, distance, score: distance, data };
             nearest.push(res);
         }
 
         // Sort the nearest neighbors by distance.
-        nearest.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        nearest.sort_by(|a, b| a.distance.total_cmp(&b.distance));
         nearest.truncate(n);
         Ok(nearest)
     }
@@ -304,18 +800,94 @@ impl Collection {
         self.dimension
     }
 
-    /// Sets the vector dimension of the collection.
+    /// Sets the vector dimension of the collection. Changing it drops
+    /// the trash and the record versions of the old dimension.
     /// * `dimension`: New vector dimension.
     pub fn set_dimension(&mut self, dimension: usize) -> Result<(), Error> {
+        self.ensure_writable()?;
+
         // This can only be set if the collection is empty.
         if !self.vectors.is_empty() {
             return Err("The collection must be empty.".into());
         }
 
+        if dimension != self.dimension {
+            self.trash.clear();
+            self.heap_size = 0;
+            if let Some(history) = self.history.as_mut() {
+                *history = RecordHistory::default();
+            }
+        }
+
         self.dimension = dimension;
         Ok(())
     }
 
+    /// Freezes or unfreezes the collection. A read-only collection
+    /// rejects inserts, updates, and deletes while serving reads.
+    /// * `read_only`: True to freeze the collection.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Returns true if the collection is read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Tags the collection with the embedding model of its vectors
+    /// like `text-embedding-3-small`. Vectors from different models
+    /// aren't comparable even if their dimensions match.
+    /// * `model`: Model identifier or None to remove the tag.
+    pub fn set_model(&mut self, model: Option<String>) {
+        self.model = model;
+    }
+
+    /// Returns the embedding model the collection is tagged with.
+    pub fn model(&self) -> Option<String> {
+        self.model.clone()
+    }
+
+    /// Ensures the model matches the model of the collection.
+    /// Untagged collections accept any model.
+    /// * `model`: Model identifier of the vectors or the query.
+    pub fn ensure_model(&self, model: &str) -> Result<(), Error> {
+        match self.model.as_deref() {
+            Some(expected) if expected != model => {
+                Err(Error::model_mismatch(model, expected))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Inserts the record like `insert` if its vector is from the
+    /// embedding model of the collection.
+    /// * `record`: Vector record to insert.
+    /// * `model`: Model identifier of the vector.
+    pub fn insert_with_model(
+        &mut self,
+        record: &Record,
+        model: &str,
+    ) -> Result<VectorID, Error> {
+        self.ensure_model(model)?;
+        self.insert(record)
+    }
+
+    /// Searches the nearest neighbors like `search` if the query is
+    /// from the embedding model of the collection.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `model`: Model identifier of the query vector.
+    pub fn search_with_model(
+        &self,
+        vector: &Vector,
+        n: usize,
+        model: &str,
+    ) -> Result<Vec<SearchResult>, Error> {
+        self.ensure_model(model)?;
+        self.search(vector, n)
+    }
+
     /// Returns the number of vector records in the collection.
     pub fn len(&self) -> usize {
         self.count
@@ -326,6 +898,36 @@ impl Collection {
         self.count == 0
     }
 
+    /// Returns the estimated memory usage of the collection in bytes.
+    /// This includes the records, the index layers, the trash, the
+    /// history, the vector sums, and the text, geo, and access indexes.
+    /// The size of the records is kept up to date on writes so this
+    /// is cheap.
+    pub fn memory_usage(&self) -> usize {
+        let upper_layers: usize = self.upper_layers.iter().map(map_size).sum();
+        let layers = vec_size(&self.base_layer)
+            + vec_size(&self.upper_layers)
+            + upper_layers;
+
+        let text_index = self.text_index.as_ref().map(TextIndex::memory_usage);
+        let geo_index = self.geo_index.as_ref().map(GeoIndex::memory_usage);
+        let history = self.history.as_ref().map(RecordHistory::memory_usage);
+
+        size_of::<Self>()
+            + map_size(&self.vectors)
+            + map_size(&self.data)
+            + self.heap_size
+            + layers
+            + vec_size(&self.slots)
+            + vec_size(&self.free_slots)
+            + vec_size(&self.trash)
+            + self.access.memory_usage()
+            + text_index.unwrap_or(0)
+            + geo_index.unwrap_or(0)
+            + history.unwrap_or(0)
+            + self.vector_sums.memory_usage()
+    }
+
     /// Checks if the collection contains a vector ID.
     /// * `id`: Vector ID to check.
     pub fn contains(&self, id: &VectorID) -> bool {
@@ -338,19 +940,95 @@ impl Collection {
 }
 
 impl Collection {
-    /// Builds the collection index from vector records.
-    /// * `config`: Collection configuration.
-    /// * `records`: List of vectors to build the index from.
-    pub fn build(config: &Config, records: &[Record]) -> Result<Self, Error> {
-        if records.is_empty() {
-            return Ok(Self::new(config));
+    /// Creates an empty collection with the given configuration.
+    /// The config is validated when building the index. Use
+    /// `try_new` to validate it upfront.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            config: *config,
+            count: 0,
+            read_only: false,
+            level_seed: 0,
+            deletes_since_repair: 0,
+            heap_size: 0,
+            dimension: 0,
+            data: HashMap::new(),
+            vectors: HashMap::new(),
+            slots: vec![],
+            free_slots: vec![],
+            base_layer: vec![],
+            upper_layers: vec![],
+            entry_point: INVALID,
+            access: AccessTracker::default(),
+            text_index: None,
+            geo_index: None,
+            trash: vec![],
+            transforms: vec![],
+            history: None,
+            model: None,
+            vector_sums: VectorSums::default(),
         }
+    }
 
-        // Ensure the number of records is within the limit.
-        if records.len() >= u32::MAX as usize {
-            let message = format!(
-                "The collection record limit is {}. Given: {}",
-                u32::MAX,
+    /// Searches the collection for the nearest neighbors.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    pub fn search(
+        &self,
+        vector: &Vector,
+        n: usize,
+    ) -> Result<Vec<SearchResult>, Error> {
+        self.validate_n(n)?;
+        let ef = self.config.ef_search;
+        let candidates = self.search_candidates(vector, ef, None)?;
+        let results = candidates.iter().take(n);
+        Ok(results.map(|candidate| self.search_result(candidate)).collect())
+    }
+
+    /// Builds the collection index from vector records.
+    /// * `config`: Collection configuration.
+    /// * `records`: List of vectors to build the index from.
+    pub fn build(config: &Config, records: &[Record]) -> Result<Self, Error> {
+        Self::build_records(config, records, Insertion::Parallel)
+    }
+
+    /// Builds the collection index like `build` but always produces
+    /// the same index from the same records. The records are inserted
+    /// in ordered batches where only the neighbor search runs in
+    /// parallel, so the build is slower than `build`.
+    /// * `config`: Collection configuration.
+    /// * `records`: List of vectors to build the index from.
+    pub fn build_deterministic(
+        config: &Config,
+        records: &[Record],
+    ) -> Result<Self, Error> {
+        Self::build_records(config, records, Insertion::Deterministic)
+    }
+
+    /// Builds the collection index from the records.
+    /// * `insertion`: Order of the insertions into the index.
+    fn build_records(
+        config: &Config,
+        records: &[Record],
+        insertion: Insertion,
+    ) -> Result<Self, Error> {
+        if records.is_empty() {
+            return Self::try_new(config);
+        }
+
+        let (vectors, data, dimension) = Self::split_records(records)?;
+        Self::build_index(config, vectors, data, dimension, insertion, None)
+    }
+
+    /// Validates the records and splits them into the vectors and
+    /// data with consecutive IDs along with the vector dimension.
+    /// * `records`: Non-empty list of records to build the index from.
+    fn split_records(records: &[Record]) -> Result<RecordParts, Error> {
+        // Ensure the number of records is within the limit.
+        if records.len() >= u32::MAX as usize {
+            let message = format!(
+                "The collection record limit is {}. Given: {}",
+                u32::MAX,
                 records.len()
             );
 
@@ -368,9 +1046,79 @@ impl Collection {
             return Err(message.into());
         }
 
+        records.par_iter().try_for_each(|i| i.vector.validate_finite())?;
+
+        let vectors = records
+            .par_iter()
+            .enumerate()
+            .map(|(i, item)| (i.into(), item.vector.clone()))
+            .collect::<HashMap<VectorID, Vector>>();
+
+        let data = records
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (i.into(), item.data.clone()))
+            .collect();
+
+        Ok((vectors, data, dimension))
+    }
+
+    /// Builds the collection index from records consumed one by one
+    /// like records parsed from a file. Unlike `build`, this doesn't
+    /// need all of the records in memory in addition to the index.
+    /// * `config`: Collection configuration.
+    /// * `records`: Records to build the index from.
+    pub fn build_from_iter(
+        config: &Config,
+        records: impl IntoIterator<Item = Record>,
+    ) -> Result<Self, Error> {
+        let mut streamed = StreamedRecords::default();
+        for record in records {
+            streamed.push(record)?;
+        }
+
+        streamed.build(config)
+    }
+
+    /// Builds the collection index from records received one by one
+    /// asynchronously like records read from a network stream. The
+    /// records are pulled until the source returns None. The index is
+    /// built after the last record which blocks the task meanwhile.
+    /// * `config`: Collection configuration.
+    /// * `next`: Function returning the future of the next record.
+    pub async fn build_from_stream<F, R>(
+        config: &Config,
+        mut next: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnMut() -> R,
+        R: Future<Output = Option<Record>>,
+    {
+        let mut streamed = StreamedRecords::default();
+        while let Some(record) = next().await {
+            streamed.push(record)?;
+        }
+
+        streamed.build(config)
+    }
+
+    /// Builds the index layers over the vectors with consecutive IDs.
+    /// The vectors must not be empty and share the same dimension.
+    /// With the checkpoint path, the build state is saved after every
+    /// chunk of the given size and resumed from the file if it exists.
+    fn build_index(
+        config: &Config,
+        vectors: HashMap<VectorID, Vector>,
+        data: HashMap<VectorID, Metadata>,
+        dimension: usize,
+        insertion: Insertion,
+        checkpoint: Option<(&str, usize)>,
+    ) -> Result<Self, Error> {
+        config.validate()?;
+
         // Find the number of layers.
 
-        let mut len = records.len();
+        let mut len = vectors.len();
         let mut layers = Vec::new();
 
         loop {
@@ -397,12 +1145,6 @@ impl Collection {
         // construction progresses, while preserving randomness in
         // each point's layer and insertion order.
 
-        let vectors = records
-            .par_iter()
-            .enumerate()
-            .map(|(i, item)| (i.into(), item.vector.clone()))
-            .collect::<HashMap<VectorID, Vector>>();
-
         // Figure out how many nodes will go on each layer.
         // This helps us allocate memory capacity for each
         // layer in advance, and also helps enable batch
@@ -419,15 +1161,35 @@ impl Collection {
         // Create index constructor.
 
         let search_pool = SearchPool::new(vectors.len());
-        let mut upper_layers = vec![vec![]; top_layer.0];
-        let base_layer = vectors
+        let mut upper_layers = vec![UpperLayer::new(); top_layer.0];
+        let mut base_layer = vectors
             .par_iter()
             .map(|_| RwLock::new(BaseNode::default()))
             .collect::<Vec<_>>();
 
+        // Resume from the saved layers of an interrupted build.
+        let mut resume = (0, 0);
+        let mut writer = None;
+        if let Some((path, _)) = checkpoint {
+            let layers = upper_layers.len();
+            let header = CheckpointHeader::new(dimension, layers, &vectors);
+            let (file, saved) = BuildCheckpoint::open(path, &header)?;
+
+            resume = (saved.layer, saved.position);
+            upper_layers = saved.upper_layers;
+            base_layer =
+                saved.base_layer.into_iter().map(RwLock::new).collect();
+            writer = Some(file);
+        }
+
+        // The first vector is never inserted with the constructor
+        // and exists in all layers, making it the entry point.
+        let entry_point = VectorID(0);
+
         let state = IndexConstruction {
             base_layer: &base_layer,
             search_pool,
+            entry_point,
             top_layer,
             vectors: &vectors,
             config,
@@ -435,28 +1197,60 @@ impl Collection {
 
         // Initialize data for layers.
 
-        for (layer, range) in ranges {
+        for (i, (layer, range)) in ranges.into_iter().enumerate() {
+            // Skip the layers completed before the checkpoint.
+            if i < resume.0 {
+                continue;
+            }
+
             let end = range.end;
+            let start = match i == resume.0 {
+                true => max(range.start, resume.1),
+                false => range.start,
+            };
 
-            range.into_par_iter().for_each(|i: usize| {
-                state.insert(&i.into(), &layer, &upper_layers)
-            });
+            match (checkpoint, writer.as_mut()) {
+                (Some((_, size)), Some(writer)) => {
+                    let mut position = start;
+                    while position < end {
+                        let next = min(position + size, end);
+                        let chunk = position..next;
+                        state.insert_range(
+                            chunk,
+                            &layer,
+                            &upper_layers,
+                            insertion,
+                        );
+                        position = next;
+
+                        let base_layer = state.base_layer;
+                        writer.save(i, position, base_layer, &upper_layers)?;
+                    }
+                }
+                _ => state.insert_range(
+                    start..end,
+                    &layer,
+                    &upper_layers,
+                    insertion,
+                ),
+            }
 
             // Copy the base layer state to the upper layer.
             if !layer.is_zero() {
-                (&state.base_layer[..end])
+                upper_layers[layer.0 - 1] = (&state.base_layer[..end])
                     .into_par_iter()
-                    .map(|zero| UpperNode::from_zero(&zero.read()))
-                    .collect_into_vec(&mut upper_layers[layer.0 - 1]);
+                    .enumerate()
+                    .map(|(i, zero)| {
+                        (i.into(), UpperNode::from_zero(&zero.read()))
+                    })
+                    .collect();
+
+                if let Some(writer) = writer.as_mut() {
+                    writer.pending.push(layer.0 - 1);
+                }
             }
         }
 
-        let data = records
-            .iter()
-            .enumerate()
-            .map(|(i, item)| (i.into(), item.data.clone()))
-            .collect();
-
         // Unwrap the base nodes for the base layer.
         let base_iter = base_layer.into_par_iter();
         let base_layer = base_iter.map(|node| node.into_inner()).collect();
@@ -464,158 +1258,2504 @@ impl Collection {
         // Add IDs to the slots.
         let slots = (0..vectors.len()).map(|i| i.into()).collect();
 
+        let size = |(id, vector)| record_size(vector, &data[id]);
+        let heap_size = vectors.par_iter().map(size).sum();
+        let vector_sums = vectors.values().collect();
+
         Ok(Self {
+            count: vectors.len(),
             data,
             vectors,
             base_layer,
             upper_layers,
             slots,
+            free_slots: vec![],
+            entry_point,
+            access: AccessTracker::default(),
+            text_index: None,
+            geo_index: None,
+            trash: vec![],
+            transforms: vec![],
+            history: None,
+            model: None,
+            vector_sums,
             dimension,
             config: *config,
-            count: records.len(),
+            read_only: false,
+            level_seed: 0,
+            deletes_since_repair: 0,
+            heap_size,
         })
     }
 
-    /// Validates a vector dimension against the collection's.
-    fn validate_dimension(&self, vector: &Vector) -> Result<(), Error> {
-        let found = vector.len();
-        let expected = self.dimension;
-
-        if found != expected {
-            Err(Error::invalid_dimension(found, expected))
-        } else {
-            Ok(())
+    /// Builds the collection index from records of the declared
+    /// dimension and handles mismatched records with the policy.
+    /// Returns the collection with the report of affected records.
+    /// Skipped records shift the vector IDs of the records after
+    /// them. Use `DimensionReport::vector_id` to find the IDs.
+    /// * `config`: Collection configuration.
+    /// * `records`: List of vectors to build the index from.
+    /// * `dimension`: Declared vector dimension of the collection.
+    /// * `policy`: How to handle the records of other dimensions.
+    pub fn build_with_policy(
+        config: &Config,
+        records: &[Record],
+        dimension: usize,
+        policy: DimensionPolicy,
+    ) -> Result<(Self, DimensionReport), Error> {
+        let mismatched = records.iter().find(|r| r.vector.len() != dimension);
+        if let (DimensionPolicy::Reject, Some(record)) = (policy, mismatched) {
+            let len = record.vector.len();
+            return Err(Error::invalid_dimension(len, dimension));
         }
-    }
 
-    /// Inserts a vector ID into the index layers.
-    fn insert_to_layers(&mut self, id: &VectorID) {
-        self.base_layer.push(BaseNode::default());
+        // The records are copied one at a time as the build takes them.
+        let mut report = DimensionReport::default();
+        let accepted = records.iter().enumerate().filter_map(|(i, record)| {
+            let len = record.vector.len();
+            if len == dimension {
+                return Some(record.clone());
+            }
 
-        let base_layer = self
-            .base_layer
-            .par_iter()
-            .map(|node| RwLock::new(*node))
-            .collect::<Vec<_>>();
+            if let DimensionPolicy::Coerce = policy {
+                let mut vector =
+                    record.vector.0[..min(len, dimension)].to_vec();
+                vector.resize(dimension, 0.0);
 
-        let top_layer = match self.upper_layers.is_empty() {
-            true => LayerID(0),
-            false => LayerID(self.upper_layers.len()),
-        };
+                match len < dimension {
+                    true => report.padded.push(i),
+                    false => report.truncated.push(i),
+                }
 
-        let state = IndexConstruction {
-            base_layer: base_layer.as_slice(),
-            search_pool: SearchPool::new(self.vectors.len()),
-            top_layer,
-            vectors: &self.vectors,
-            config: &self.config,
-        };
+                return Some(Record {
+                    vector: vector.into(),
+                    data: record.data.clone(),
+                });
+            }
 
-        // Insert new vector into the contructor.
-        state.insert(id, &top_layer, &self.upper_layers);
+            report.skipped.push(i);
+            None
+        });
 
-        // Update the base layer with the new state.
-        let iter = state.base_layer.into_par_iter();
-        self.base_layer = iter.map(|node| *node.read()).collect();
+        let mut collection = Self::build_from_iter(config, accepted)?;
+        collection.dimension = dimension;
+        Ok((collection, report))
     }
 
-    /// Removes a vector ID from all index layers.
-    fn delete_from_layers(&mut self, id: &VectorID) {
-        // Remove the vector from the base layer.
-        let base_node = &mut self.base_layer[id.0 as usize];
-        let index = base_node.par_iter().position_first(|x| *x == *id);
-        if let Some(index) = index {
-            base_node.set(index, &INVALID);
-        }
-
-        // Remove the vector from the upper layers.
-        for layer in LayerID(self.upper_layers.len()).descend() {
-            let upper_layer = match layer.0 > 0 {
-                true => &mut self.upper_layers[layer.0 - 1],
-                false => break,
-            };
+    /// Builds the collection index using a dedicated thread pool.
+    /// This prevents a large build from occupying the global
+    /// thread pool shared with the rest of the application.
+    /// * `config`: Collection configuration.
+    /// * `records`: List of vectors to build the index from.
+    /// * `threads`: Number of threads. 0 to use the number of CPUs.
+    pub fn build_with_threads(
+        config: &Config,
+        records: &[Record],
+        threads: usize,
+    ) -> Result<Self, Error> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("sahomedb-build-{i}"))
+            .build()?;
 
-            let node = &mut upper_layer[id.0 as usize];
-            let index = node.0.par_iter().position_first(|x| *x == *id);
+        pool.install(|| Self::build(config, records))
+    }
 
-            if let Some(index) = index {
-                node.set(index, &INVALID);
+    /// Builds the collection index within the resource limits so
+    /// the build leaves resources for the rest of the application.
+    /// * `config`: Collection configuration.
+    /// * `records`: List of vectors to build the index from.
+    /// * `limits`: Resource limits of the build.
+    pub fn build_with_limits(
+        config: &Config,
+        records: &[Record],
+        limits: &BuildLimits,
+    ) -> Result<Self, Error> {
+        if let Some(max) = limits.max_memory {
+            let dimension = records.first().map_or(0, |r| r.vector.len());
+            let vector = dimension * size_of::<f32>() + size_of::<BaseNode>();
+            if records.len().saturating_mul(vector) > max {
+                return Err(Error::memory_limit(max));
             }
         }
+
+        let insertion = match limits.chunk_size {
+            0 => Insertion::Parallel,
+            size => Insertion::Throttled(size, limits.pause),
+        };
+
+        let low_priority = limits.low_priority;
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(limits.max_threads)
+            .thread_name(|i| format!("sahomedb-build-{i}"))
+            .spawn_handler(move |worker| {
+                let mut builder = thread::Builder::new();
+                if let Some(name) = worker.name() {
+                    builder = builder.name(name.to_string());
+                }
+
+                if let Some(size) = worker.stack_size() {
+                    builder = builder.stack_size(size);
+                }
+
+                builder.spawn(move || {
+                    if low_priority {
+                        lower_thread_priority();
+                    }
+
+                    worker.run()
+                })?;
+
+                Ok(())
+            })
+            .build()?;
+
+        pool.install(|| Self::build_records(config, records, insertion))
     }
-}
 
-/// A record containing a vector and its associated data.
-#[pyclass(module = "sahomedb.collection")]
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct Record {
-    /// The vector embedding.
-    #[pyo3(get, set)]
-    pub vector: Vector,
-    /// Data associated with the vector.
-    #[pyo3(get)]
-    pub data: Metadata,
-}
+    /// Builds the collection index like `build` but saves the build
+    /// state to the checkpoint file after every chunk of inserted
+    /// vectors. If the file exists, the build resumes from it instead
+    /// of starting over. The file is removed when the build completes.
+    /// Each save only appends the index nodes changed by the chunk and
+    /// resuming with records other than the saved ones fails.
+    /// * `config`: Collection configuration.
+    /// * `records`: List of vectors, the same ones when resuming.
+    /// * `path`: File path of the checkpoint.
+    /// * `every`: Number of vectors inserted between checkpoints.
+    pub fn build_with_checkpoint(
+        config: &Config,
+        records: &[Record],
+        path: &str,
+        every: usize,
+    ) -> Result<Self, Error> {
+        if records.is_empty() {
+            return Self::try_new(config);
+        }
 
-// Any modifications to the Python methods should be reflected in:
-// - py/tests/test_collection.py
-// - py/sahomedb/collection.pyi
-#[pymethods]
-impl Record {
-    #[new]
-    fn py_new(vector: Vec<f32>, data: &PyAny) -> Self {
-        let vector = Vector::from(vector);
-        let data = Metadata::from(data);
-        Self::new(&vector, &data)
+        let (vectors, data, dimension) = Self::split_records(records)?;
+        let checkpoint = Some((path, max(every, 1)));
+        let insertion = Insertion::Parallel;
+        let collection = Self::build_index(
+            config, vectors, data, dimension, insertion, checkpoint,
+        )?;
+
+        if Path::new(path).exists() {
+            remove_file(path)?;
+        }
+
+        Ok(collection)
     }
 
-    /// Generates a random record for testing.
-    /// * `dimension`: Vector dimension.
-    #[staticmethod]
-    pub fn random(dimension: usize) -> Self {
-        let vector = Vector::random(dimension);
-        let data = random::<usize>().into();
-        Self::new(&vector, &data)
+    /// Searches the nearest neighbors with data matching the filter.
+    /// The filter is applied to the candidates found in the index
+    /// so selective filters may return fewer than `n` results.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `filter`: Condition the record data must match.
+    pub fn search_with_filter(
+        &self,
+        vector: &Vector,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<SearchResult>, Error> {
+        Ok(self.explain_search(vector, n, Some(filter))?.results)
     }
 
-    /// Generates many random records for testing.
-    /// * `dimension`: Vector dimension.
-    /// * `len`: Number of records to generate.
-    #[staticmethod]
-    pub fn many_random(dimension: usize, len: usize) -> Vec<Self> {
-        (0..len).map(|_| Self::random(dimension)).collect()
+    /// Searches the nearest neighbors like `search_with_filter` and
+    /// returns the statistics of the search along with the results.
+    /// This helps to debug why an expected neighbor is missing.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `filter`: Optional condition the record data must match.
+    pub fn explain_search(
+        &self,
+        vector: &Vector,
+        n: usize,
+        filter: Option<&Filter>,
+    ) -> Result<SearchExplain, Error> {
+        self.explain_oversampled(vector, n, filter, 0)
     }
 
-    fn __repr__(&self) -> String {
-        format!("{:?}", self)
+    /// Searches the nearest neighbors with data matching the filter
+    /// and oversamples the candidates for selective filters. The EF
+    /// is doubled until `n` candidates match or it reaches `max_ef`.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `filter`: Condition the record data must match.
+    /// * `max_ef`: Max number of candidates to search for.
+    pub fn search_oversampled(
+        &self,
+        vector: &Vector,
+        n: usize,
+        filter: &Filter,
+        max_ef: usize,
+    ) -> Result<Vec<SearchResult>, Error> {
+        let explain =
+            self.explain_oversampled(vector, n, Some(filter), max_ef)?;
+        Ok(explain.results)
     }
-}
 
-impl Record {
-    /// Creates a new record with a vector and data.
-    pub fn new(vector: &Vector, data: &Metadata) -> Self {
-        Self { vector: vector.clone(), data: data.clone() }
+    /// Chooses the strategy to search with the filter from the share
+    /// of sampled records matching it. Filters served by the spatial
+    /// index are always compared directly.
+    /// * `n`: Number of neighbors to return.
+    /// * `filter`: Condition the record data must match.
+    pub fn plan_filter(&self, n: usize, filter: &Filter) -> FilterStrategy {
+        if self.geo_index_for(filter).is_some() {
+            return FilterStrategy::Exact;
+        }
+
+        let sample = self.data.values().take(PLAN_SAMPLE);
+        let (sampled, matched) = sample.fold((0, 0), |(total, count), data| {
+            (total + 1, count + filter.matches(data) as usize)
+        });
+
+        if sampled == 0 {
+            return FilterStrategy::PostFilter;
+        }
+
+        let selectivity = matched as f32 / sampled as f32;
+        let estimate = (selectivity * self.vectors.len() as f32) as usize;
+        let ef = max(self.config.ef_search, n);
+        if estimate <= max(PLAN_EXACT_LIMIT, ef) {
+            return FilterStrategy::Exact;
+        }
+
+        // Expected number of candidates to find n matches.
+        let needed = (n as f32 / selectivity).ceil() as usize;
+        match needed <= ef {
+            true => FilterStrategy::PostFilter,
+            false => FilterStrategy::Oversample(min(needed * 2, self.len())),
+        }
     }
-}
 
-/// The collection nearest neighbor search result.
-#[pyclass(module = "sahomedb.collection")]
-#[derive(Serialize, Deserialize, Debug)]
-pub struct SearchResult {
-    /// Vector ID.
-    #[pyo3(get)]
-    pub id: u32,
-    /// Distance between the query to the collection vector.
-    #[pyo3(get)]
-    pub distance: f32,
-    /// Data associated with the vector.
-    #[pyo3(get)]
-    pub data: Metadata,
-}
+    /// Searches the nearest neighbors with data matching the filter
+    /// using the strategy chosen by `plan_filter`.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `filter`: Condition the record data must match.
+    pub fn search_planned(
+        &self,
+        vector: &Vector,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<SearchResult>, Error> {
+        self.validate_n(n)?;
+        match self.plan_filter(n, filter) {
+            FilterStrategy::PostFilter => {
+                self.search_with_filter(vector, n, filter)
+            }
+            FilterStrategy::Oversample(max_ef) => {
+                self.search_oversampled(vector, n, filter, max_ef)
+            }
+            FilterStrategy::Exact => {
+                let ids = match self.geo_candidates(filter) {
+                    Some(ids) => ids,
+                    None => self
+                        .data
+                        .iter()
+                        .filter(|(_, data)| filter.matches(data))
+                        .map(|(id, _)| *id)
+                        .collect(),
+                };
+
+                let candidates = self.exact_candidates(vector, &ids)?;
+                let results = candidates.iter().take(n);
+                Ok(results.map(|c| self.search_result(c)).collect())
+            }
+        }
+    }
 
-#[pymethods]
-impl SearchResult {
-    fn __repr__(&self) -> String {
-        format!("{:?}", self)
+    /// Deletes the records with data matching the filter like `delete`
+    /// so they go to the trash if it's enabled. Returns the vector IDs
+    /// of the deleted records.
+    /// * `filter`: Condition the record data must match.
+    pub fn delete_matching(
+        &mut self,
+        filter: &Filter,
+    ) -> Result<Vec<VectorID>, Error> {
+        self.ensure_writable()?;
+
+        let mut ids: Vec<VectorID> = self
+            .data
+            .par_iter()
+            .filter(|(_, data)| filter.matches(data))
+            .map(|(id, _)| *id)
+            .collect();
+
+        ids.sort();
+        for id in ids.iter() {
+            self.delete(id)?;
+        }
+
+        Ok(ids)
     }
+
+    /// Searches the nearest neighbors and doubles the EF until the top
+    /// `n` results are the same as with the previous EF or it reaches
+    /// `max_ef`. Easy queries stop early while hard ones consider more
+    /// candidates without tuning the EF per query.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `max_ef`: Max number of candidates to search for.
+    pub fn search_adaptive(
+        &self,
+        vector: &Vector,
+        n: usize,
+        max_ef: usize,
+    ) -> Result<Vec<SearchResult>, Error> {
+        self.validate_n(n)?;
+        let mut ef = max(self.config.ef_search, n);
+        let mut previous: Option<Vec<VectorID>> = None;
+
+        loop {
+            let candidates = self.search_candidates(vector, ef, None)?;
+            let top: Vec<VectorID> =
+                candidates.iter().take(n).map(|c| c.vector_id).collect();
+
+            // Stop if the results are stable or the index has no
+            // more records to find.
+            let stable = previous.as_ref() == Some(&top);
+            let exhausted = candidates.len() < ef;
+            if stable || exhausted || ef >= max_ef {
+                let results = candidates.iter().take(n);
+                return Ok(results.map(|c| self.search_result(c)).collect());
+            }
+
+            previous = Some(top);
+            ef = min(ef.saturating_mul(2), max_ef);
+        }
+    }
+
+    /// Searches the nearest neighbors like `search_oversampled` and
+    /// returns the statistics of the last search round.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `filter`: Optional condition the record data must match.
+    /// * `max_ef`: Max number of candidates to search for.
+    pub fn explain_oversampled(
+        &self,
+        vector: &Vector,
+        n: usize,
+        filter: Option<&Filter>,
+        max_ef: usize,
+    ) -> Result<SearchExplain, Error> {
+        self.validate_n(n)?;
+        let mut explain = SearchExplain::default();
+        let matches = |candidate: &&Candidate| {
+            let data = &self.data[&candidate.vector_id];
+            filter.is_none_or(|filter| filter.matches(data))
+        };
+
+        // Geo filters served by the spatial index are usually
+        // selective so compare the vectors of the matches directly.
+        let start = Instant::now();
+        let candidates = match filter.and_then(|f| self.geo_candidates(f)) {
+            Some(ids) => {
+                explain.exact = true;
+                self.exact_candidates(vector, &ids)?
+            }
+            None => {
+                explain.ef = match filter {
+                    Some(_) => max(self.config.ef_search, n),
+                    None => self.config.ef_search,
+                };
+
+                loop {
+                    explain.layers.clear();
+                    let layers = Some(&mut explain.layers);
+                    let candidates =
+                        self.search_candidates(vector, explain.ef, layers)?;
+
+                    // Stop if enough candidates match the filter or
+                    // the index has no more records to find.
+                    let found = candidates.iter().filter(matches).take(n);
+                    let exhausted = candidates.len() < explain.ef;
+                    if found.count() >= n || exhausted || explain.ef >= max_ef {
+                        break candidates;
+                    }
+
+                    explain.ef = min(explain.ef.saturating_mul(2), max_ef);
+                }
+            }
+        };
+
+        explain.candidates = candidates.len();
+        explain.search_duration = start.elapsed();
+
+        let start = Instant::now();
+        for candidate in candidates.iter() {
+            if explain.results.len() >= n {
+                break;
+            }
+
+            let data = &self.data[&candidate.vector_id];
+            if filter.is_some_and(|filter| !filter.matches(data)) {
+                explain.filtered += 1;
+                continue;
+            }
+
+            explain.results.push(self.search_result(candidate));
+        }
+
+        explain.filter_duration = start.elapsed();
+        Ok(explain)
+    }
+
+    /// Checks the records like `insert` without modifying the collection
+    /// so large imports can be validated before inserting any record.
+    /// Returns the index and the error message of each record that
+    /// would be rejected. The memory added by each record is estimated
+    /// and duplicates within the records are found only if identical.
+    /// * `records`: Vector records to insert.
+    pub fn validate_records(
+        &self,
+        records: &[Record],
+    ) -> Result<Vec<(usize, String)>, Error> {
+        self.ensure_writable()?;
+
+        // The first record sets the dimension of an empty collection.
+        let mut dimension = match self.dimension {
+            0 if self.vectors.is_empty() => None,
+            dimension => Some(dimension),
+        };
+
+        // Records over the limits are rejected unless others are evicted.
+        let evicts = self.config.eviction.is_some();
+        let max_records = self.config.max_records.filter(|_| !evicts);
+        let max_memory = self.config.max_memory.filter(|_| !evicts);
+
+        // Memory added by a record besides its vector and data.
+        let overhead = size_of::<(VectorID, Vector)>()
+            + size_of::<(VectorID, Metadata)>()
+            + size_of::<BaseNode>()
+            + size_of::<VectorID>()
+            + 2;
+
+        let mut accepted = self.count;
+        let mut memory = self.memory_usage();
+        let mut vectors = HashSet::new();
+        let mut errors = vec![];
+        for (i, record) in records.iter().enumerate() {
+            let result = self.transform(&record.vector).and_then(|vector| {
+                vector.validate_finite()?;
+                let expected = *dimension.get_or_insert(vector.len());
+                if vector.len() != expected {
+                    let len = vector.len();
+                    return Err(Error::invalid_dimension(len, expected));
+                }
+
+                // Duplicates update the existing record or are skipped.
+                if self.config.dedup.is_some() {
+                    let bits: Vec<u32> =
+                        vector.0.iter().map(|v| v.to_bits()).collect();
+                    if !vectors.insert(bits)
+                        || self.find_duplicate(&vector)?.is_some()
+                    {
+                        return Ok(None);
+                    }
+                }
+
+                match (max_records, max_memory) {
+                    (Some(max), _) if accepted >= max => {
+                        Err(Error::record_limit(max))
+                    }
+                    (_, Some(max)) if memory >= max => {
+                        Err(Error::memory_limit(max))
+                    }
+                    _ => Ok(Some(record_size(&vector, &record.data))),
+                }
+            });
+
+            match result {
+                Ok(Some(size)) => {
+                    accepted += 1;
+                    memory += size + overhead;
+                }
+                Ok(None) => {}
+                Err(err) => errors.push((i, err.message().to_string())),
+            }
+        }
+
+        Ok(errors)
+    }
+
+    /// Sets the transforms applied in order to the vectors of the
+    /// inserted records and the search queries. The stored vectors
+    /// aren't transformed again so set the transforms before inserting
+    /// or build the collection from already transformed vectors.
+    /// * `transforms`: Transforms like normalization or projection.
+    pub fn set_transforms(
+        &mut self,
+        transforms: Vec<Transform>,
+    ) -> Result<(), Error> {
+        self.ensure_writable()?;
+        self.transforms = transforms;
+        Ok(())
+    }
+
+    /// Returns the transforms applied to the vectors.
+    pub fn transforms(&self) -> &[Transform] {
+        &self.transforms
+    }
+
+    /// Returns a new collection with the vectors projected on their
+    /// principal components. The projection is added to the transforms
+    /// so the queries with the original dimension keep working.
+    /// The records keep their vector IDs and the trash and history
+    /// are projected too so they stay usable in the new collection.
+    /// * `dimension`: Vector dimension of the new collection.
+    pub fn reduce_dimension(&self, dimension: usize) -> Result<Self, Error> {
+        let mut ids: Vec<VectorID> = self.vectors.keys().copied().collect();
+        ids.sort();
+
+        let vectors: Vec<Vector> =
+            ids.iter().map(|id| self.vectors[id].clone()).collect();
+        let pca = Transform::pca(&vectors, dimension)?;
+        let project = |vector: &Vector| {
+            pca.iter().try_fold(vector.clone(), |v, t| t.apply(&v))
+        };
+
+        let records: Vec<Record> = ids
+            .par_iter()
+            .zip(vectors.par_iter())
+            .map(|(id, vector)| {
+                Ok(Record::new(&project(vector)?, &self.data[id]))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        // The index is built over consecutive IDs so its links are
+        // mapped back to the vector IDs of the records.
+        let reduced = Self::build(&self.config, &records)?;
+        let reduced = reduced.map_ids(&ids, self.slots.len());
+
+        let mut trash = self.trash.clone();
+        for trashed in trash.iter_mut() {
+            trashed.record.vector = project(&trashed.record.vector)?;
+        }
+
+        let history = match &self.history {
+            Some(history) => {
+                let mut versions = history.versions().to_vec();
+                for version in versions.iter_mut() {
+                    version.record.vector = project(&version.record.vector)?;
+                }
+
+                let created = history.created_times().clone();
+                Some(RecordHistory::new(created, versions))
+            }
+            None => None,
+        };
+
+        let mut collection = Self {
+            config: self.config,
+            data: self.data.clone(),
+            vectors: reduced.vectors,
+            slots: self.slots.clone(),
+            free_slots: self.free_slots.clone(),
+            base_layer: reduced.base_layer,
+            upper_layers: reduced.upper_layers,
+            entry_point: reduced.entry_point,
+            access: self.access.clone(),
+            text_index: self.text_index.clone(),
+            geo_index: self.geo_index.clone(),
+            trash,
+            transforms: [self.transforms.clone(), pca].concat(),
+            history,
+            model: self.model.clone(),
+            vector_sums: reduced.vector_sums,
+            read_only: self.read_only,
+            level_seed: self.level_seed,
+            deletes_since_repair: 0,
+            heap_size: 0,
+            count: self.count,
+            dimension: reduced.dimension,
+        };
+
+        collection.heap_size = collection.measure_heap_size();
+        Ok(collection)
+    }
+
+    /// Applies the migrations in order to the data of every record.
+    /// The data is only replaced if all records migrate successfully
+    /// so a failed cast leaves the collection unchanged.
+    /// Returns the number of changed records.
+    /// * `migrations`: Changes to the data fields like renames.
+    pub fn migrate_data(
+        &mut self,
+        migrations: &[Migration],
+    ) -> Result<usize, Error> {
+        self.ensure_writable()?;
+
+        let migrate = |(id, data): (&VectorID, &Metadata)| {
+            let mut migrated: Option<Metadata> = None;
+            for migration in migrations {
+                let current = migrated.as_ref().unwrap_or(data);
+                if let Some(data) = migration.apply(current)? {
+                    migrated = Some(data);
+                }
+            }
+
+            Ok(migrated.map(|data| (*id, data)))
+        };
+
+        let changes: Vec<_> = self
+            .data
+            .par_iter()
+            .map(migrate)
+            .collect::<Result<Vec<_>, Error>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        for (id, data) in changes.iter() {
+            self.retire_version(id);
+            self.set_record(id, self.vectors[id].clone(), data.clone());
+            self.index_data(id);
+            self.write_version(id);
+        }
+
+        Ok(changes.len())
+    }
+
+    /// Enables or disables keeping the past versions of the records
+    /// so they can be read as of a past time. Disabling the versioning
+    /// drops the history.
+    /// * `versioned`: True to keep the record versions.
+    pub fn set_versioned(&mut self, versioned: bool) {
+        match versioned {
+            true => {
+                self.history.get_or_insert_with(RecordHistory::default);
+            }
+            false => self.history = None,
+        }
+    }
+
+    /// Returns true if the collection keeps the record versions.
+    pub fn is_versioned(&self) -> bool {
+        self.history.is_some()
+    }
+
+    /// Returns the version of the record that was current at the
+    /// timestamp if the record existed then.
+    /// * `id`: Vector ID of the record.
+    /// * `timestamp`: Unix timestamp in seconds.
+    pub fn get_as_of(
+        &self,
+        id: &VectorID,
+        timestamp: u64,
+    ) -> Result<Option<Record>, Error> {
+        let history = self.history_as_of(timestamp)?;
+        if self.contains(id) && history.created(id) <= timestamp {
+            return Ok(Some(Record::new(&self.vectors[id], &self.data[id])));
+        }
+
+        let version = history.versions().iter().find(|version| {
+            version.id == *id && version.is_current_at(timestamp)
+        });
+        Ok(version.map(|version| version.record.clone()))
+    }
+
+    /// Searches the nearest neighbors among the record versions that
+    /// were current at the timestamp. The search is exhaustive like
+    /// `true_search` because the index only covers the current records.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `timestamp`: Unix timestamp in seconds.
+    pub fn search_as_of(
+        &self,
+        vector: &Vector,
+        n: usize,
+        timestamp: u64,
+    ) -> Result<Vec<SearchResult>, Error> {
+        self.validate_n(n)?;
+        let history = self.history_as_of(timestamp)?;
+
+        let vector = &self.transform(vector)?;
+        self.validate_dimension(vector)?;
+
+        let current = self
+            .vectors
+            .iter()
+            .filter(|(id, _)| history.created(id) <= timestamp)
+            .map(|(id, vec)| (id, vec, &self.data[id]));
+
+        let past = history
+            .versions()
+            .iter()
+            .filter(|version| version.is_current_at(timestamp))
+            .map(|version| {
+                (&version.id, &version.record.vector, &version.record.data)
+            });
+
+        let mut nearest: Vec<SearchResult> = current
+            .chain(past)
+            .map(|(id, vec, data)| {
+                let distance = vector.distance(vec);
+                let data = data.clone();
+                SearchResult { id: id.0, distance, score: distance, data }
+            })
+            .collect();
+
+        nearest.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        nearest.truncate(n);
+        Ok(nearest)
+    }
+
+    /// Creates a spatial index over the location in the data field
+    /// to serve the geo filters and the geo search. This replaces
+    /// the existing geo index. See `GeoPoint` for the format.
+    /// * `field`: Name of the data object field storing the location.
+    pub fn create_geo_index(&mut self, field: &str) {
+        let mut index = GeoIndex::new(field);
+        for (id, data) in self.data.iter() {
+            index.insert(id, data);
+        }
+
+        self.geo_index = Some(index);
+    }
+
+    /// Returns the records nearest to the location sorted by the
+    /// geo distance. The distance of the results is in meters.
+    /// * `center`: Location to measure the distance from.
+    /// * `n`: Number of records to return.
+    pub fn geo_search(
+        &self,
+        center: &GeoPoint,
+        n: usize,
+    ) -> Result<Vec<SearchResult>, Error> {
+        self.validate_n(n)?;
+        let index = match self.geo_index.as_ref() {
+            Some(index) => index,
+            None => return Err(Error::geo_index_not_found()),
+        };
+
+        let map_result = |(id, distance): (VectorID, f32)| {
+            self.touch(&id);
+            let data = self.data[&id].clone();
+            SearchResult { id: id.0, distance, score: distance, data }
+        };
+
+        Ok(index.nearest(center, n).into_iter().map(map_result).collect())
+    }
+
+    /// Counts the records per distinct value of the data field.
+    /// Use `aggregate::facet_counts` to count search results instead.
+    /// * `field`: Name of the data object field to count.
+    pub fn facet_counts(&self, field: &str) -> Vec<FacetCount> {
+        facet_counts(self.data.values(), field)
+    }
+
+    /// Counts the records per range of the numeric data field.
+    /// Use `aggregate::histogram` to aggregate search results instead.
+    /// * `field`: Name of the data object field to aggregate.
+    /// * `interval`: Width of each bucket. Must be positive.
+    pub fn histogram(
+        &self,
+        field: &str,
+        interval: f32,
+    ) -> Result<Vec<HistogramBucket>, Error> {
+        histogram(self.data.values(), field, interval)
+    }
+
+    /// Summarizes the distribution of the stored vectors from the
+    /// sums kept up to date on writes without walking the vectors.
+    /// See `aggregate::vector_stats` for the details.
+    /// * `interval`: Width of each norm bucket. Must be positive.
+    pub fn vector_stats(&self, interval: f32) -> Result<VectorStats, Error> {
+        self.vector_sums.stats(interval)
+    }
+
+    /// Encodes the collection in the compact binary format.
+    /// Numbers are little-endian, lengths are varints, and the node
+    /// links are written without their trailing invalid IDs.
+    /// The text and geo indexes are rebuilt when decoding.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.config(&self.config);
+        writer.usize(self.dimension);
+        writer.usize(self.count);
+        writer.u64(self.level_seed);
+        writer.usize(self.deletes_since_repair);
+        writer.u32(self.entry_point.0);
+        writer.bool(self.read_only);
+
+        // Write the records in the slot order.
+        writer.usize(self.slots.len());
+        for id in self.slots.iter() {
+            writer.bool(id.is_valid());
+            if id.is_valid() {
+                self.vectors[id].0.iter().for_each(|x| writer.f32(*x));
+                writer.metadata(&self.data[id]);
+            }
+        }
+
+        writer.usize(self.free_slots.len());
+        self.free_slots.iter().for_each(|id| writer.u32(id.0));
+
+        writer.usize(self.base_layer.len());
+        self.base_layer.iter().for_each(|node| writer.links(&node.0));
+
+        writer.usize(self.upper_layers.len());
+        for layer in self.upper_layers.iter() {
+            let mut nodes: Vec<_> = layer.iter().collect();
+            nodes.sort_by_key(|(id, _)| **id);
+
+            writer.usize(nodes.len());
+            for (id, node) in nodes {
+                writer.u32(id.0);
+                writer.links(&node.0);
+            }
+        }
+
+        // The optional parts are written as sections in tag order.
+        let (clock, records) = self.access.parts();
+        if clock > 0 {
+            writer.section(section::ACCESS, |writer| {
+                writer.u64(clock);
+                writer.usize(records.len());
+                for (id, access) in records {
+                    writer.u32(id.0);
+                    writer.varint(access.last);
+                    writer.varint(access.count);
+                }
+            });
+        }
+
+        if let Some(index) = &self.text_index {
+            writer.section(section::TEXT_INDEX, |writer| {
+                writer.usize(index.fields().len());
+                index.fields().iter().for_each(|field| writer.str(field));
+            });
+        }
+
+        if let Some(index) = &self.geo_index {
+            writer.section(section::GEO_INDEX, |writer| {
+                writer.str(index.field());
+            });
+        }
+
+        if !self.trash.is_empty() {
+            writer.section(section::TRASH, |writer| {
+                writer.usize(self.trash.len());
+                for trashed in self.trash.iter() {
+                    writer.u32(trashed.id.0);
+                    writer.u64(trashed.deleted_at);
+                    writer.vector(&trashed.record.vector);
+                    writer.metadata(&trashed.record.data);
+                }
+            });
+        }
+
+        if !self.transforms.is_empty() {
+            writer.section(section::TRANSFORMS, |writer| {
+                writer.usize(self.transforms.len());
+                self.transforms.iter().for_each(|t| writer.transform(t));
+            });
+        }
+
+        if let Some(history) = &self.history {
+            writer.section(section::HISTORY, |writer| {
+                let mut created: Vec<_> =
+                    history.created_times().iter().collect();
+                created.sort();
+                writer.usize(created.len());
+                for (id, timestamp) in created {
+                    writer.u32(id.0);
+                    writer.u64(*timestamp);
+                }
+
+                writer.usize(history.versions().len());
+                for version in history.versions() {
+                    writer.u32(version.id.0);
+                    writer.u64(version.from);
+                    writer.u64(version.to);
+                    writer.vector(&version.record.vector);
+                    writer.metadata(&version.record.data);
+                }
+            });
+        }
+
+        if let Some(model) = &self.model {
+            writer.section(section::MODEL, |writer| writer.str(model));
+        }
+
+        writer.into_bytes()
+    }
+
+    /// Decodes the collection from the binary format and summarizes
+    /// its content. This fails like `from_bytes` with the reason if
+    /// the data is truncated or corrupted.
+    /// * `bytes`: Bytes encoded with `to_bytes`.
+    pub fn inspect(bytes: &[u8]) -> Result<FormatSummary, Error> {
+        let collection = Self::from_bytes(bytes)?;
+        let upper_layers = collection.upper_layers.iter().map(|l| l.len());
+
+        Ok(FormatSummary {
+            version: Reader::new(bytes)?.version(),
+            size: bytes.len(),
+            dimension: collection.dimension,
+            records: collection.count,
+            slots: collection.slots.len(),
+            free_slots: collection.free_slots.len(),
+            layers: once(collection.base_layer.len())
+                .chain(upper_layers)
+                .collect(),
+            entry_point: collection.entry_point.0,
+            read_only: collection.read_only,
+            trash: collection.trash.len(),
+        })
+    }
+
+    /// Decodes the collection from the compact binary format.
+    /// * `bytes`: Bytes encoded with `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(bytes)?;
+        let mut collection = Self::try_new(&reader.config()?)?;
+        collection.dimension = reader.usize()?;
+        collection.count = reader.usize()?;
+        collection.level_seed = reader.u64()?;
+        collection.deletes_since_repair = reader.usize()?;
+        collection.entry_point = VectorID(reader.u32()?);
+        collection.read_only = reader.bool()?;
+
+        let slots = reader.length()?;
+        for index in 0..slots {
+            if !reader.bool()? {
+                collection.slots.push(INVALID);
+                continue;
+            }
+
+            let id = VectorID::from(index);
+            let vector = (0..collection.dimension).map(|_| reader.f32());
+            let vector = vector.collect::<Result<Vec<f32>, Error>>()?;
+            collection.vectors.insert(id, Vector(vector));
+            collection.data.insert(id, reader.metadata()?);
+            collection.slots.push(id);
+        }
+
+        let free_slots = reader.length()?;
+        for _ in 0..free_slots {
+            collection.free_slots.push(VectorID(reader.u32()?));
+        }
+
+        let nodes = reader.length()?;
+        for _ in 0..nodes {
+            let mut node = BaseNode::default();
+            reader.links(&mut node.0)?;
+            collection.base_layer.push(node);
+        }
+
+        let layers = reader.length()?;
+        for _ in 0..layers {
+            let nodes = reader.length()?;
+            let mut layer = UpperLayer::with_capacity(nodes);
+            for _ in 0..nodes {
+                let id = VectorID(reader.u32()?);
+                let mut node = UpperNode::default();
+                reader.links(&mut node.0)?;
+                layer.insert(id, node);
+            }
+
+            collection.upper_layers.push(layer);
+        }
+
+        while let Some((tag, mut reader)) = reader.section()? {
+            collection.read_section(tag, &mut reader)?;
+            if !reader.is_done() {
+                let detail = format!("Unexpected data in section {tag}.");
+                return Err(Error::invalid_format(&detail));
+            }
+        }
+
+        collection.validate_structure()?;
+        collection.heap_size = collection.measure_heap_size();
+        collection.vector_sums = collection.vectors.values().collect();
+        Ok(collection)
+    }
+
+    /// Decodes an optional section of the binary format.
+    /// * `tag`: Tag of the section from the `section` module.
+    /// * `reader`: Reader over the section content.
+    fn read_section(
+        &mut self,
+        tag: u8,
+        reader: &mut Reader,
+    ) -> Result<(), Error> {
+        match tag {
+            section::ACCESS => {
+                let clock = reader.u64()?;
+                let len = reader.length()?;
+                let mut records = HashMap::with_capacity(len);
+                for _ in 0..len {
+                    let id = VectorID(reader.u32()?);
+                    let last = reader.varint()?;
+                    let count = reader.varint()?;
+                    records.insert(id, Access { last, count });
+                }
+
+                self.access = AccessTracker::from_parts(clock, records);
+            }
+            section::TEXT_INDEX => {
+                let len = reader.length()?;
+                let fields = (0..len).map(|_| reader.string());
+                let fields = fields.collect::<Result<Vec<String>, Error>>()?;
+                self.create_text_index(fields);
+            }
+            section::GEO_INDEX => {
+                self.create_geo_index(&reader.string()?);
+            }
+            section::TRASH => {
+                let len = reader.length()?;
+                for _ in 0..len {
+                    let id = VectorID(reader.u32()?);
+                    let deleted_at = reader.u64()?;
+                    let vector = reader.vector()?;
+                    let record = Record::new(&vector, &reader.metadata()?);
+                    self.trash.push(TrashedRecord { id, record, deleted_at });
+                }
+            }
+            section::TRANSFORMS => {
+                let len = reader.length()?;
+                for _ in 0..len {
+                    self.transforms.push(reader.transform()?);
+                }
+            }
+            section::HISTORY => {
+                let len = reader.length()?;
+                let mut created = HashMap::with_capacity(len);
+                for _ in 0..len {
+                    created.insert(VectorID(reader.u32()?), reader.u64()?);
+                }
+
+                let len = reader.length()?;
+                let mut versions = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let id = VectorID(reader.u32()?);
+                    let from = reader.u64()?;
+                    let to = reader.u64()?;
+                    let vector = reader.vector()?;
+                    let record = Record::new(&vector, &reader.metadata()?);
+                    versions.push(RecordVersion { id, record, from, to });
+                }
+
+                self.history = Some(RecordHistory::new(created, versions));
+            }
+            section::MODEL => self.model = Some(reader.string()?),
+            _ => {
+                let detail = format!("Unknown section: {tag}.");
+                return Err(Error::invalid_format(&detail));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decodes the collection stored by the database. Collections
+    /// stored before the database used the binary format are migrated.
+    /// * `bytes`: Value stored by the database.
+    pub(crate) fn from_stored(bytes: &[u8]) -> Result<Self, Error> {
+        match bytes.starts_with(MAGIC) {
+            true => Self::from_bytes(bytes),
+            false => Self::from_legacy(bytes),
+        }
+    }
+
+    /// Decodes the collection serialized by the database before it
+    /// used the binary format. The records keep their vector IDs but
+    /// the index is rebuilt since the old index linked the inserted
+    /// nodes to themselves instead of their neighbors.
+    /// * `bytes`: Collection serialized with bincode.
+    fn from_legacy(bytes: &[u8]) -> Result<Self, Error> {
+        let mut legacy: LegacyCollection = bincode::deserialize(bytes)?;
+        let LegacyConfig { ef_construction, ef_search, ml } = legacy.config;
+        let config = Config::new(ef_construction, ef_search, ml);
+
+        // Deleted records left free slots so the index is built over
+        // consecutive IDs and mapped back to the slots of the records.
+        let mut ids = vec![];
+        let mut vectors = HashMap::new();
+        let mut data = HashMap::new();
+        for (index, id) in legacy.slots.iter().enumerate() {
+            if !id.is_valid() {
+                continue;
+            }
+
+            let vector = legacy.vectors.remove(id);
+            let metadata = legacy.data.remove(id);
+            let (vector, metadata) = match (vector, metadata) {
+                (Some(vector), Some(metadata)) if id.0 as usize == index => {
+                    (vector, metadata)
+                }
+                _ => return Err(Error::invalid_format("Invalid record slot.")),
+            };
+
+            let built_id = VectorID::from(ids.len());
+            vectors.insert(built_id, vector);
+            data.insert(built_id, metadata);
+            ids.push(*id);
+        }
+
+        let collection = match ids.is_empty() {
+            true => Self::try_new(&config)?,
+            false => Self::build_index(
+                &config,
+                vectors,
+                data,
+                legacy.dimension,
+                Insertion::Parallel,
+                None,
+            )?,
+        };
+
+        let mut collection = collection.map_ids(&ids, legacy.slots.len());
+        collection.dimension = legacy.dimension;
+        Ok(collection)
+    }
+
+    /// Maps the consecutive vector IDs of the built collection to the
+    /// vector IDs of its records. Slots without a record become free.
+    /// * `ids`: Vector ID of each record in the build order.
+    /// * `len`: Number of slots of the mapped collection.
+    fn map_ids(mut self, ids: &[VectorID], len: usize) -> Self {
+        let to_id = |id: &VectorID| match id.is_valid() {
+            true => ids[id.0 as usize],
+            false => INVALID,
+        };
+
+        let mut slots = vec![INVALID; len];
+        let mut base_layer = vec![BaseNode::default(); len];
+        for (index, node) in self.base_layer.iter().enumerate() {
+            let slot = ids[index].0 as usize;
+            slots[slot] = ids[index];
+            base_layer[slot] = BaseNode(node.0.map(|id| to_id(&id)));
+        }
+
+        let upper_layers = self.upper_layers.iter().map(|layer| {
+            let node = |(id, node): (&VectorID, &UpperNode)| {
+                (to_id(id), UpperNode(node.0.map(|id| to_id(&id))))
+            };
+
+            layer.iter().map(node).collect()
+        });
+
+        let vectors = self.vectors.into_iter();
+        let data = self.data.into_iter();
+        let free_slots = (0..len).filter(|slot| !slots[*slot].is_valid());
+
+        self.vectors = vectors.map(|(id, v)| (to_id(&id), v)).collect();
+        self.data = data.map(|(id, d)| (to_id(&id), d)).collect();
+        self.free_slots = free_slots.map(VectorID::from).collect();
+        self.upper_layers = upper_layers.collect();
+        self.entry_point = to_id(&self.entry_point);
+        self.base_layer = base_layer;
+        self.slots = slots;
+        self
+    }
+
+    /// Ensures the decoded index only refers to existing slots
+    /// so that corrupted data can't cause a panic on search.
+    /// Links to free slots are left by deletions and skipped.
+    fn validate_structure(&self) -> Result<(), Error> {
+        let is_record = |id: &VectorID| self.vectors.contains_key(id);
+        let is_slot = |id: &VectorID| (id.0 as usize) < self.slots.len();
+        let is_free = |id: &VectorID| is_slot(id) && !is_record(id);
+
+        let base_links = self.base_layer.iter().flat_map(|node| node.0.iter());
+        let upper_nodes = self.upper_layers.iter().flatten();
+        let nodes_valid = upper_nodes.clone().all(|(id, _)| is_record(id));
+        let upper_links = upper_nodes.flat_map(|(_, node)| node.0.iter());
+        let links_valid = base_links
+            .chain(upper_links)
+            .all(|id| !id.is_valid() || is_slot(id));
+
+        // The search starts from the entry point so it must be on
+        // all upper layers with nodes or the search would skip them.
+        let entry_valid = self.upper_layers.iter().all(|layer| {
+            layer.is_empty() || layer.contains_key(&self.entry_point)
+        });
+
+        // A slot listed twice would be given to two records.
+        let mut free = HashSet::new();
+        let free_valid =
+            self.free_slots.iter().all(|id| is_free(id) && free.insert(*id));
+
+        // The kept records are searched and restored like the others.
+        let trashed = self.trash.iter().map(|trashed| &trashed.record);
+        let versions = self.history.iter().flat_map(|h| h.versions());
+        let kept = trashed.chain(versions.map(|version| &version.record));
+        let kept_valid =
+            kept.into_iter().all(|r| r.vector.len() == self.dimension);
+
+        let valid = links_valid
+            && nodes_valid
+            && entry_valid
+            && free_valid
+            && kept_valid
+            && self.count == self.vectors.len()
+            && self.base_layer.len() == self.slots.len()
+            && (is_record(&self.entry_point) || self.vectors.is_empty());
+
+        match valid {
+            true => Ok(()),
+            false => Err(Error::invalid_format("Invalid index structure.")),
+        }
+    }
+
+    /// Traverses the index layers to find the nearest neighbors.
+    /// * `vector`: Vector to search.
+    /// * `ef`: Number of candidates to consider in the base layer.
+    /// * `stats`: Optional list to record the layer statistics to.
+    fn search_candidates(
+        &self,
+        vector: &Vector,
+        ef: usize,
+        stats: Option<&mut Vec<LayerExplain>>,
+    ) -> Result<Vec<Candidate>, Error> {
+        let (candidates, _) = self.search_layers(vector, ef, 1, None, stats)?;
+        Ok(candidates)
+    }
+
+    /// Searches the index layers for the nearest candidates. The base
+    /// layer search also starts from records spread across the slots
+    /// when more than one entry point is requested. Returns the
+    /// candidates and true if the search stopped at the deadline.
+    /// * `entry_points`: Number of entry points of the base layer.
+    /// * `deadline`: Time to stop the search at if limited.
+    /// * `stats`: Statistics of each traversed layer if requested.
+    fn search_layers(
+        &self,
+        vector: &Vector,
+        ef: usize,
+        entry_points: usize,
+        deadline: Option<Instant>,
+        stats: Option<&mut Vec<LayerExplain>>,
+    ) -> Result<(Vec<Candidate>, bool), Error> {
+        // Early return if the collection is empty.
+        if self.vectors.is_empty() {
+            return Ok((vec![], false));
+        }
+
+        let vector = &self.transform(vector)?;
+        self.search_transformed(vector, ef, entry_points, deadline, stats)
+    }
+
+    /// Searches the index layers like `search_layers` for a vector
+    /// already transformed like the stored vectors.
+    fn search_transformed(
+        &self,
+        vector: &Vector,
+        ef: usize,
+        entry_points: usize,
+        deadline: Option<Instant>,
+        mut stats: Option<&mut Vec<LayerExplain>>,
+    ) -> Result<(Vec<Candidate>, bool), Error> {
+        let mut search = Search::default();
+        search.deadline = deadline;
+
+        // Early return if the collection is empty.
+        if self.vectors.is_empty() {
+            return Ok((vec![], false));
+        }
+
+        // Ensure the vector dimension matches the collection dimension.
+        self.validate_dimension(vector)?;
+
+        // Start the search from the cached entry point.
+        if !self.entry_point.is_valid() {
+            return Err("Unable to initiate search.".into());
+        }
+
+        search.visited.resize_capacity(self.slots.len());
+        search.push(&self.entry_point, vector, &self.vectors);
+
+        let (mut hops, mut distances) = (0, 0);
+        for layer in self.entry_layer().descend() {
+            let start = Instant::now();
+            let entry_point = search.iter().next().map(|c| c.vector_id);
+            search.ef = if layer.is_zero() { ef } else { 5 };
+
+            if layer.0 == 0 {
+                for id in self.spread_ids(entry_points.saturating_sub(1)) {
+                    search.push(&id, vector, &self.vectors);
+                }
+
+                let layer = self.base_layer.as_slice();
+                search.search(layer, vector, &self.vectors, M * 2);
+            } else {
+                let layer = &self.upper_layers[layer.0 - 1];
+                search.search(layer, vector, &self.vectors, M);
+            }
+
+            if let Some(stats) = stats.as_mut() {
+                stats.push(LayerExplain {
+                    layer: layer.0,
+                    entry_point: entry_point.unwrap_or(INVALID).0,
+                    hops: search.hops - hops,
+                    distance_computations: search.distances - distances,
+                    duration: start.elapsed(),
+                });
+            }
+
+            (hops, distances) = (search.hops, search.distances);
+            if search.stopped {
+                break;
+            }
+
+            if !layer.is_zero() {
+                search.cull();
+            }
+        }
+
+        Ok((search.iter().collect(), search.stopped))
+    }
+
+    /// Returns up to `count` vector IDs at even intervals of the slots.
+    /// Records are usually inserted in batches of similar vectors so
+    /// these are likely in different regions of the vector space.
+    fn spread_ids(&self, count: usize) -> Vec<VectorID> {
+        if count == 0 {
+            return vec![];
+        }
+
+        let step = max(1, self.slots.len() / count);
+        let ids = self.slots.iter().step_by(step).filter(|id| id.is_valid());
+        ids.take(count).copied().collect()
+    }
+
+    /// Returns the records as candidates sorted by their distance
+    /// to the vector computed directly without the index.
+    /// * `vector`: Vector to search.
+    /// * `ids`: Vector IDs of the records to compare.
+    fn exact_candidates(
+        &self,
+        vector: &Vector,
+        ids: &[VectorID],
+    ) -> Result<Vec<Candidate>, Error> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let vector = &self.transform(vector)?;
+        self.validate_dimension(vector)?;
+        let to_candidate = |id: &VectorID| {
+            let distance = vector.distance(&self.vectors[id]).into();
+            Candidate { distance, vector_id: *id }
+        };
+
+        let mut candidates: Vec<Candidate> =
+            ids.iter().map(to_candidate).collect();
+        candidates.sort();
+        Ok(candidates)
+    }
+
+    /// Maps the nearest neighbor candidate to a search result.
+    fn search_result(&self, candidate: &Candidate) -> SearchResult {
+        self.touch(&candidate.vector_id);
+        let id = candidate.vector_id.0;
+        let distance = candidate.distance.0;
+        let data = self.data[&candidate.vector_id].clone();
+        SearchResult { id, distance, score: distance, data }
+    }
+
+    /// Inserts the record whose vector is already transformed.
+    /// * `record`: Vector record to insert.
+    /// * `target`: Vector ID to use instead of the next free slot.
+    fn insert_transformed(
+        &mut self,
+        record: &Record,
+        target: Option<&VectorID>,
+    ) -> Result<VectorID, Error> {
+        self.ensure_writable()?;
+
+        // Ensure the number of records is within the limit.
+        let full = self.slots.len() == u32::MAX as usize;
+        if full && self.free_slots.is_empty() {
+            return Err(Error::collection_limit());
+        }
+
+        record.vector.validate_finite()?;
+
+        // Ensure the vector dimension matches the collection config.
+        // If it's the first record, set the dimension.
+        if self.vectors.is_empty() && self.dimension == 0 {
+            self.dimension = record.vector.len();
+        } else if record.vector.len() != self.dimension {
+            let len = record.vector.len();
+            let err = Error::invalid_dimension(len, self.dimension);
+            return Err(err);
+        }
+
+        // Return the existing record if the vector is a duplicate.
+        if let Some(policy) = self.config.dedup {
+            if let Some(id) = self.find_duplicate(&record.vector)? {
+                // The record can't be created with the target vector ID.
+                if target.is_some() {
+                    return Err(Error::duplicate_record(id.0));
+                }
+
+                if policy == DedupPolicy::Merge {
+                    self.retire_version(&id);
+                    self.merge_data(&id, &record.data);
+                    self.write_version(&id);
+                }
+
+                self.touch(&id);
+                return Ok(id);
+            }
+        }
+
+        // Evict records to make room when the policy is enabled.
+        if let Some(policy) = self.config.eviction {
+            self.evict(policy)?;
+        }
+
+        // Ensure the number of records is within the configured limit.
+        if let Some(max) = self.config.max_records {
+            if self.count >= max {
+                return Err(Error::record_limit(max));
+            }
+        }
+
+        // Ensure the memory usage is within the configured limit.
+        if let Some(max) = self.config.max_memory {
+            if self.memory_usage() >= max {
+                return Err(Error::memory_limit(max));
+            }
+        }
+
+        // Reuse a slot freed by a deleted record if available.
+        // Otherwise, create a new vector ID using the next slot.
+        // The slot is taken after all checks so a failed insert
+        // doesn't leave allocated slots behind.
+        let id = match target {
+            Some(target) => {
+                self.claim_slot(target);
+                *target
+            }
+            None => match self.free_slots.pop() {
+                Some(id) => id,
+                None => {
+                    let id: VectorID = self.slots.len().into();
+                    self.slots.push(INVALID);
+                    id
+                }
+            },
+        };
+
+        // Insert the new vector and data.
+        self.set_record(&id, record.vector.clone(), record.data.clone());
+        self.index_data(&id);
+
+        // Mark the slot as used by the new vector ID.
+        self.slots[id.0 as usize] = id;
+        self.touch(&id);
+        self.write_version(&id);
+
+        // Update the collection count.
+        self.count += 1;
+
+        // This operation is last because it depends on
+        // the updated vectors data.
+        self.insert_to_layers(&id);
+
+        Ok(id)
+    }
+
+    /// Takes the slot of the vector ID and frees the slots skipped
+    /// before it so they are reused by the next inserts.
+    /// * `id`: Vector ID of the new record.
+    fn claim_slot(&mut self, id: &VectorID) {
+        let index = id.0 as usize;
+        while self.slots.len() < index {
+            self.free_slots.push(self.slots.len().into());
+            self.slots.push(INVALID);
+            self.base_layer.push(BaseNode::default());
+        }
+
+        if index == self.slots.len() {
+            self.slots.push(INVALID);
+        } else {
+            self.free_slots.retain(|free| free != id);
+        }
+    }
+
+    /// Returns the vector transformed by the collection transforms.
+    /// * `vector`: Vector of a record or a search query.
+    fn transform<'a>(
+        &self,
+        vector: &'a Vector,
+    ) -> Result<Cow<'a, Vector>, Error> {
+        let mut vector = Cow::Borrowed(vector);
+        for transform in self.transforms.iter() {
+            vector = Cow::Owned(transform.apply(&vector)?);
+        }
+
+        Ok(vector)
+    }
+
+    /// Returns the record with its vector transformed.
+    /// * `record`: Vector record to insert or update.
+    fn transform_record<'a>(
+        &self,
+        record: &'a Record,
+    ) -> Result<Cow<'a, Record>, Error> {
+        if self.transforms.is_empty() {
+            return Ok(Cow::Borrowed(record));
+        }
+
+        let vector = self.transform(&record.vector)?;
+        Ok(Cow::Owned(Record::new(&vector, &record.data)))
+    }
+
+    /// Validates the number of requested results against the limit.
+    fn validate_n(&self, n: usize) -> Result<(), Error> {
+        match self.config.max_n {
+            Some(max) if n > max => Err(Error::result_limit(n, max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Validates a vector dimension against the collection's
+    /// and ensures the vector values are finite.
+    fn validate_dimension(&self, vector: &Vector) -> Result<(), Error> {
+        vector.validate_finite()?;
+
+        let found = vector.len();
+        let expected = self.dimension;
+
+        if found != expected {
+            Err(Error::invalid_dimension(found, expected))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Inserts a vector ID into the index layers.
+    fn insert_to_layers(&mut self, id: &VectorID) {
+        let index = id.0 as usize;
+
+        // Reset the node of a reused slot or allocate a new one.
+        if index < self.base_layer.len() {
+            self.base_layer[index] = BaseNode::default();
+        } else {
+            self.base_layer.push(BaseNode::default());
+        }
+
+        // The first vector inserted becomes the entry point.
+        if !self.entry_point.is_valid() {
+            self.entry_point = *id;
+        }
+
+        self.relink(&[*id]);
+
+        // Link the vector on the upper layers up to its random level.
+        // It becomes the entry point if it creates a new top layer.
+        let top_layer = self.entry_layer();
+        let level = self.random_level();
+        for layer in 1..=level.0 {
+            self.insert_to_upper_layer(id, LayerID(layer), top_layer);
+        }
+
+        if level > top_layer {
+            self.entry_point = *id;
+        }
+    }
+
+    /// Draws the level of a new vector from the exponential
+    /// distribution so that each upper layer has about `ml` times
+    /// the vectors of the layer below. The level is capped at one
+    /// above the current top layer.
+    fn random_level(&mut self) -> LayerID {
+        let mut rng = StdRng::seed_from_u64(self.level_seed);
+        self.level_seed = rng.gen();
+
+        let ml = self.config.ml;
+        if !(ml > 0.0 && ml < 1.0) {
+            return LayerID(0);
+        }
+
+        // Uniform sample within (0, 1] to avoid the log of zero.
+        let uniform = 1.0 - rng.gen::<f32>();
+        let level = (uniform.ln() / ml.ln()).floor() as usize;
+        LayerID(min(level, self.upper_layers.len() + 1))
+    }
+
+    /// Links the vector to its nearest neighbors on the upper layer
+    /// and links the neighbors back to it. Layers above the top layer
+    /// are created as needed.
+    /// * `id`: Vector ID to insert.
+    /// * `layer`: Upper layer to insert into.
+    /// * `top_layer`: Layer of the entry point before the insert.
+    fn insert_to_upper_layer(
+        &mut self,
+        id: &VectorID,
+        layer: LayerID,
+        top_layer: LayerID,
+    ) {
+        if layer.0 > self.upper_layers.len() {
+            self.upper_layers.push(UpperLayer::new());
+        }
+
+        let nodes = &mut self.upper_layers[layer.0 - 1];
+        nodes.insert(*id, UpperNode::default());
+
+        // There is no other vector to link to on a new top layer.
+        if layer > top_layer {
+            return;
+        }
+
+        let vector = &self.vectors[id];
+        let mut search = Search::default();
+        search.visited.resize_capacity(self.slots.len());
+        search.push(&self.entry_point, vector, &self.vectors);
+
+        for current in top_layer.descend().take_while(|l| *l >= layer) {
+            search.ef = match current == layer {
+                true => self.config.ef_construction,
+                false => 5,
+            };
+
+            let nodes = &self.upper_layers[current.0 - 1];
+            search.search(nodes, vector, &self.vectors, M);
+            if current > layer {
+                search.cull();
+            }
+        }
+
+        let neighbors: Vec<Candidate> =
+            search.iter().filter(|c| c.vector_id != *id).take(M).collect();
+
+        let nodes = &mut self.upper_layers[layer.0 - 1];
+        let mut node = UpperNode::default();
+        let links = neighbors.iter().map(|c| c.vector_id);
+        links.enumerate().for_each(|(i, link)| node.0[i] = link);
+        nodes.insert(*id, node);
+
+        // Insert the vector to the neighbors keeping the links sorted.
+        for neighbor in neighbors.iter() {
+            let other = &self.vectors[&neighbor.vector_id];
+            let ordering = |link: &VectorID| match self.vectors.get(link) {
+                Some(vector) => {
+                    let distance = other.distance(vector);
+                    OrderedFloat::from(distance).cmp(&neighbor.distance)
+                }
+                None => Ordering::Greater,
+            };
+
+            // Stale links can lead to reused slots not on the layer.
+            let node = match nodes.get_mut(&neighbor.vector_id) {
+                Some(node) => node,
+                None => continue,
+            };
+
+            let position = node.0.binary_search_by(ordering);
+            node.insert(position.unwrap_or_else(|i| i), id);
+        }
+    }
+
+    /// Selects the base layer neighbors of the vectors again
+    /// and links the selected neighbors back to them.
+    /// * `ids`: Vector IDs to link in the base layer.
+    fn relink(&mut self, ids: &[VectorID]) {
+        for id in ids {
+            self.base_layer[id.0 as usize] = BaseNode::default();
+        }
+
+        let base_layer = self
+            .base_layer
+            .par_iter()
+            .map(|node| RwLock::new(*node))
+            .collect::<Vec<_>>();
+
+        let state = IndexConstruction {
+            base_layer: base_layer.as_slice(),
+            search_pool: SearchPool::new(self.slots.len()),
+            entry_point: self.entry_point,
+            top_layer: self.entry_layer(),
+            vectors: &self.vectors,
+            config: &self.config,
+        };
+
+        // Insert the vectors into the contructor.
+        for id in ids {
+            state.insert(id, &LayerID(0), &self.upper_layers);
+        }
+
+        // Update the base layer with the new state.
+        let iter = state.base_layer.into_par_iter();
+        self.base_layer = iter.map(|node| *node.read()).collect();
+    }
+
+    /// Removes a vector ID from all index layers. Only its neighbors
+    /// are unlinked from it so the cost doesn't grow with the index.
+    /// Other nodes linking to it keep the link until the slot is
+    /// reused and the search skips the link meanwhile.
+    fn delete_from_layers(&mut self, id: &VectorID) {
+        let index = id.0 as usize;
+
+        // Move the entry point before its links are removed.
+        if self.entry_point == *id {
+            self.entry_point = self.replace_entry_point(id);
+        }
+
+        // Clear the node and remove the links back to it.
+        let node = take(&mut self.base_layer[index]);
+        for link in node.iter().take_while(|link| link.is_valid()) {
+            self.base_layer[link.0 as usize].remove(id);
+        }
+
+        // Do the same for the upper layers containing the vector.
+        for upper_layer in self.upper_layers.iter_mut() {
+            let node = match upper_layer.remove(id) {
+                Some(node) => node,
+                None => continue,
+            };
+
+            for link in node.0.iter().take_while(|link| link.is_valid()) {
+                if let Some(other) = upper_layer.get_mut(link) {
+                    other.remove(id);
+                }
+            }
+        }
+    }
+
+    /// Indexes the record data in the existing text and geo indexes.
+    fn index_data(&mut self, id: &VectorID) {
+        self.unindex_data(id);
+
+        if let Some(index) = self.text_index.as_mut() {
+            index.insert(id, &self.data[id]);
+        }
+
+        if let Some(index) = self.geo_index.as_mut() {
+            index.insert(id, &self.data[id]);
+        }
+    }
+
+    /// Removes the record from the existing text and geo indexes.
+    fn unindex_data(&mut self, id: &VectorID) {
+        if let Some(index) = self.text_index.as_mut() {
+            index.remove(id);
+        }
+
+        if let Some(index) = self.geo_index.as_mut() {
+            index.remove(id);
+        }
+    }
+
+    /// Returns the spatial index if it can serve the geo filter.
+    fn geo_index_for(&self, filter: &Filter) -> Option<&GeoIndex> {
+        let index = self.geo_index.as_ref()?;
+        let field = match filter {
+            Filter::GeoRadius(field, _, _) => field,
+            Filter::GeoBoundingBox(field, _) => field,
+            _ => return None,
+        };
+
+        (field == index.field()).then_some(index)
+    }
+
+    /// Returns the records matching the geo filter using the
+    /// spatial index. None if the index can't serve the filter.
+    fn geo_candidates(&self, filter: &Filter) -> Option<Vec<VectorID>> {
+        let index = self.geo_index_for(filter)?;
+        match filter {
+            Filter::GeoRadius(_, center, radius) => {
+                Some(index.within_radius(center, *radius))
+            }
+            Filter::GeoBoundingBox(_, area) => Some(index.within_box(area)),
+            _ => None,
+        }
+    }
+
+    /// Maps the relevance scores to search results.
+    fn ranked_results(
+        &self,
+        ranked: Vec<(VectorID, f32)>,
+    ) -> Vec<SearchResult> {
+        let map_result = |(id, score): (VectorID, f32)| {
+            self.touch(&id);
+            let distance = 1.0 / (1.0 + score);
+            let data = self.data[&id].clone();
+            SearchResult { id: id.0, distance, score: distance, data }
+        };
+
+        ranked.into_iter().map(map_result).collect()
+    }
+
+    /// Returns an error if the collection is read-only.
+    fn ensure_writable(&self) -> Result<(), Error> {
+        match self.read_only {
+            true => Err(Error::read_only()),
+            false => Ok(()),
+        }
+    }
+
+    /// Returns the vector ID of the record with a vector within
+    /// the configured dedup distance from the vector. The vector
+    /// must be transformed already like the stored vectors.
+    fn find_duplicate(
+        &self,
+        vector: &Vector,
+    ) -> Result<Option<VectorID>, Error> {
+        let ef = self.config.ef_search;
+        let (candidates, _) =
+            self.search_transformed(vector, ef, 1, None, None)?;
+        let nearest = candidates.first();
+        let max_distance = OrderedFloat(self.config.dedup_distance);
+        let duplicate = nearest.filter(|c| c.distance <= max_distance);
+        Ok(duplicate.map(|candidate| candidate.vector_id))
+    }
+
+    /// Keeps the current version of the record in the history
+    /// before it's replaced or removed if versioning is enabled.
+    /// The versions past the retention are dropped meanwhile.
+    fn retire_version(&mut self, id: &VectorID) {
+        if let Some(history) = self.history.as_mut() {
+            let now = unix_time();
+            let record = Record::new(&self.vectors[id], &self.data[id]);
+            history.retire(id, record, now);
+
+            if let Some(retention) = self.config.history_retention {
+                history.expire(now.saturating_sub(retention as u64));
+            }
+        }
+    }
+
+    /// Returns the history to read the records as of the timestamp.
+    /// Returns an error if the versions current at the timestamp
+    /// may have been dropped by the retention.
+    /// * `timestamp`: Unix timestamp in seconds.
+    fn history_as_of(&self, timestamp: u64) -> Result<&RecordHistory, Error> {
+        let history =
+            self.history.as_ref().ok_or_else(Error::history_disabled)?;
+
+        if let Some(retention) = self.config.history_retention {
+            let start = unix_time().saturating_sub(retention as u64);
+            if timestamp < start {
+                return Err(Error::history_expired(retention));
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// Records the write time of the current version of the record.
+    fn write_version(&mut self, id: &VectorID) {
+        if let Some(history) = self.history.as_mut() {
+            history.insert(id, unix_time());
+        }
+    }
+
+    /// Merges the data into the data of the existing record.
+    fn merge_data(&mut self, id: &VectorID, data: &Metadata) {
+        let existing = self.data.get_mut(id).unwrap();
+        self.heap_size -= existing.heap_size();
+        match (&mut *existing, data) {
+            (Metadata::Object(existing), Metadata::Object(new)) => {
+                existing.extend(new.clone());
+            }
+            (existing, new) => *existing = new.clone(),
+        }
+
+        self.heap_size += existing.heap_size();
+        self.index_data(id);
+    }
+
+    /// Records an access to the vector ID for the eviction policy.
+    fn touch(&self, id: &VectorID) {
+        if self.config.eviction.is_some() {
+            self.access.touch(id);
+        }
+    }
+
+    /// Returns true if the collection reached its configured limits.
+    fn is_full(&self) -> bool {
+        if let Some(max) = self.config.max_records {
+            if self.count >= max {
+                return true;
+            }
+        }
+
+        match self.config.max_memory {
+            Some(max) => self.memory_usage() >= max,
+            None => false,
+        }
+    }
+
+    /// Stores the vector and data of the record and keeps the heap
+    /// size and the vector sums of the records up to date.
+    /// * `id`: Vector ID of the record.
+    /// * `vector`: Vector of the record.
+    /// * `data`: Data of the record.
+    fn set_record(&mut self, id: &VectorID, vector: Vector, data: Metadata) {
+        self.heap_size += record_size(&vector, &data);
+        self.vector_sums.add(&vector);
+        if let Some(vector) = self.vectors.insert(*id, vector) {
+            self.heap_size -= vec_size(&vector.0);
+            self.vector_sums.remove(&vector);
+        }
+
+        if let Some(data) = self.data.insert(*id, data) {
+            self.heap_size -= data.heap_size();
+        }
+    }
+
+    /// Returns the heap size of the records and the trash by
+    /// walking them. Used once when the collection is decoded.
+    fn measure_heap_size(&self) -> usize {
+        let size = |(id, vector)| record_size(vector, &self.data[id]);
+        let records: usize = self.vectors.par_iter().map(size).sum();
+        let trash = self.trash.iter().map(|t| &t.record);
+        let trash: usize = trash.map(|r| record_size(&r.vector, &r.data)).sum();
+        records + trash
+    }
+
+    /// Removes the record from the index and the collection data.
+    /// The record must exist in the collection.
+    /// * `id`: Vector ID to remove.
+    fn remove(&mut self, id: &VectorID) {
+        self.delete_from_layers(id);
+        self.retire_version(id);
+
+        // Update the collection data.
+        self.unindex_data(id);
+        if let (Some(vector), Some(data)) =
+            (self.vectors.remove(id), self.data.remove(id))
+        {
+            self.heap_size -= record_size(&vector, &data);
+            self.vector_sums.remove(&vector);
+        }
+
+        // Make the slot invalid and free it for the next insert.
+        self.slots[id.0 as usize] = INVALID;
+        self.free_slots.push(*id);
+        self.access.remove(id);
+
+        // Update the collection count.
+        self.count -= 1;
+
+        // Repair the index when enough links have been removed.
+        self.deletes_since_repair += 1;
+        if let Some(every) = self.config.repair_every {
+            if self.deletes_since_repair >= every {
+                self.repair();
+            }
+        }
+    }
+
+    /// Evicts records until the collection is within its limits.
+    /// * `policy`: Policy to choose the record to evict.
+    fn evict(&mut self, policy: EvictionPolicy) -> Result<(), Error> {
+        while !self.is_empty() && self.is_full() {
+            let ids = self.vectors.keys();
+            let id = match self.access.victim(ids, policy) {
+                Some(id) => id,
+                None => break,
+            };
+
+            // Evicted records skip the trash to free the memory.
+            self.remove(&id);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the top layer containing the entry point.
+    fn entry_layer(&self) -> LayerID {
+        let layers = self.upper_layers.iter();
+        let is_member =
+            |layer: &&UpperLayer| layer.contains_key(&self.entry_point);
+
+        LayerID(layers.take_while(is_member).count())
+    }
+
+    /// Finds a new entry point to replace the removed vector ID.
+    fn replace_entry_point(&self, removed: &VectorID) -> VectorID {
+        let index = removed.0 as usize;
+        let is_other = |id: &&VectorID| *id != removed && self.contains(id);
+
+        // Prefer the nearest neighbor from the highest layer since
+        // it's linked to the same layers as the removed vector. The
+        // new entry point must stay on all upper layers with nodes.
+        for layer in self.upper_layers.iter().rev() {
+            let node = match layer.get(removed) {
+                Some(node) => node,
+                None => continue,
+            };
+
+            let on_layer =
+                |id: &&VectorID| *id != removed && layer.contains_key(id);
+
+            if let Some(id) = node.0.iter().find(on_layer) {
+                return *id;
+            }
+
+            // The removed vector isn't linked to the rest of the layer.
+            if let Some(id) = layer.keys().filter(on_layer).min() {
+                return *id;
+            }
+        }
+
+        if let Some(id) = self.base_layer[index].iter().find(is_other) {
+            return *id;
+        }
+
+        // Fallback to the first valid vector ID from the slots.
+        let slots = self.slots.as_slice().into_par_iter();
+        slots.find_first(|id| is_other(id)).copied().unwrap_or(INVALID)
+    }
+}
+
+/// A record containing a vector and its associated data.
+#[pyclass(module = "sahomedb.collection")]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Record {
+    /// The vector embedding.
+    #[pyo3(get, set)]
+    pub vector: Vector,
+    /// Data associated with the vector.
+    #[pyo3(get)]
+    pub data: Metadata,
+}
+
+// Any modifications to the Python methods should be reflected in:
+// - py/tests/test_collection.py
+// - py/sahomedb/collection.pyi
+#[pymethods]
+impl Record {
+    #[new]
+    fn py_new(vector: Vec<f32>, data: &PyAny) -> Self {
+        let vector = Vector::from(vector);
+        let data = Metadata::from(data);
+        Self::new(&vector, &data)
+    }
+
+    /// Generates a random record for testing.
+    /// * `dimension`: Vector dimension.
+    #[staticmethod]
+    pub fn random(dimension: usize) -> Self {
+        let vector = Vector::random(dimension);
+        let data = random::<usize>().into();
+        Self::new(&vector, &data)
+    }
+
+    /// Generates many random records for testing.
+    /// * `dimension`: Vector dimension.
+    /// * `len`: Number of records to generate.
+    #[staticmethod]
+    pub fn many_random(dimension: usize, len: usize) -> Vec<Self> {
+        (0..len).map(|_| Self::random(dimension)).collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl Record {
+    /// Creates a new record with a vector and data.
+    pub fn new(vector: &Vector, data: &Metadata) -> Self {
+        Self { vector: vector.clone(), data: data.clone() }
+    }
+}
+
+/// The collection nearest neighbor search result.
+#[pyclass(module = "sahomedb.collection")]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchResult {
+    /// Vector ID.
+    #[pyo3(get)]
+    pub id: u32,
+    /// Distance between the query to the collection vector.
+    #[pyo3(get)]
+    pub distance: f32,
+    /// Score of the result by the requested score mode.
+    /// Same as the distance if no score mode is requested.
+    #[pyo3(get)]
+    pub score: f32,
+    /// Data associated with the vector.
+    #[pyo3(get)]
+    pub data: Metadata,
+}
+
+#[pymethods]
+impl SearchResult {
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// The records consumed so far by a build from an iterator or stream.
+#[derive(Default)]
+struct StreamedRecords {
+    vectors: HashMap<VectorID, Vector>,
+    data: HashMap<VectorID, Metadata>,
+    dimension: Option<usize>,
+}
+
+impl StreamedRecords {
+    /// Validates the record and assigns it the next vector ID.
+    /// * `record`: Record to add to the build.
+    fn push(&mut self, record: Record) -> Result<(), Error> {
+        let id = self.vectors.len();
+        if id >= u32::MAX as usize - 1 {
+            return Err(Error::collection_limit());
+        }
+
+        record.vector.validate_finite()?;
+
+        let len = record.vector.len();
+        let expected = *self.dimension.get_or_insert(len);
+        if len != expected {
+            return Err(Error::invalid_dimension(len, expected));
+        }
+
+        self.vectors.insert(id.into(), record.vector);
+        self.data.insert(id.into(), record.data);
+        Ok(())
+    }
+
+    /// Builds the index over the consumed records.
+    /// * `config`: Collection configuration.
+    fn build(self, config: &Config) -> Result<Collection, Error> {
+        let Self { vectors, data, dimension } = self;
+        match dimension {
+            Some(dimension) => {
+                let insertion = Insertion::Parallel;
+                Collection::build_index(
+                    config, vectors, data, dimension, insertion, None,
+                )
+            }
+            None => Collection::try_new(config),
+        }
+    }
+}
+
+/// The vectors and data with consecutive IDs and their dimension.
+type RecordParts =
+    (HashMap<VectorID, Vector>, HashMap<VectorID, Metadata>, usize);
+
+/// The first entry of a build checkpoint file to ensure the build
+/// resumes with the same records.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CheckpointHeader {
+    dimension: usize,
+    len: usize,
+    /// Number of the upper layers being built.
+    layers: usize,
+    /// Hash of the vectors in the order of their IDs.
+    vectors_hash: u64,
+}
+
+impl CheckpointHeader {
+    /// Creates the header of the build over the vectors.
+    /// * `dimension`: Dimension of the vectors.
+    /// * `layers`: Number of the upper layers.
+    /// * `vectors`: Vectors with consecutive IDs.
+    fn new(
+        dimension: usize,
+        layers: usize,
+        vectors: &HashMap<VectorID, Vector>,
+    ) -> Self {
+        // FNV-1a over the words keeps the hash stable across builds.
+        let fnv = |hash: u64, word: u64| (hash ^ word).wrapping_mul(FNV_PRIME);
+        let hash_vector = |i: usize| {
+            let values = vectors[&i.into()].0.iter();
+            values.fold(FNV_OFFSET, |hash, value| {
+                fnv(hash, value.to_bits() as u64)
+            })
+        };
+
+        let len = vectors.len();
+        let hashes: Vec<u64> =
+            (0..len).into_par_iter().map(hash_vector).collect();
+        let vectors_hash = hashes.into_iter().fold(FNV_OFFSET, fnv);
+        Self { dimension, len, layers, vectors_hash }
+    }
+}
+
+/// The changes of the index saved after a chunk of the build.
+#[derive(Serialize, Deserialize, Debug)]
+struct CheckpointDelta {
+    /// Position of the layer being built in the build order.
+    layer: usize,
+    /// The next vector ID to insert into the layer.
+    position: usize,
+    /// Base layer nodes changed since the previous save.
+    base_nodes: Vec<(VectorID, BaseNode)>,
+    /// Upper layers completed since the previous save.
+    upper_layers: Vec<(usize, UpperLayer)>,
+}
+
+/// The build state restored from a checkpoint file.
+struct SavedBuild {
+    layer: usize,
+    position: usize,
+    base_layer: Vec<BaseNode>,
+    upper_layers: Vec<UpperLayer>,
+}
+
+impl SavedBuild {
+    /// Applies the saved changes to the build state.
+    /// * `delta`: Changes saved after a chunk.
+    fn apply(&mut self, delta: CheckpointDelta) -> Result<(), Error> {
+        for (id, node) in delta.base_nodes {
+            match self.base_layer.get_mut(id.0 as usize) {
+                Some(saved) => *saved = node,
+                None => return Err(Error::checkpoint_mismatch()),
+            }
+        }
+
+        for (index, layer) in delta.upper_layers {
+            match self.upper_layers.get_mut(index) {
+                Some(saved) => *saved = layer,
+                None => return Err(Error::checkpoint_mismatch()),
+            }
+        }
+
+        self.layer = delta.layer;
+        self.position = delta.position;
+        Ok(())
+    }
+}
+
+/// The checkpoint file of an index build. The file starts with the
+/// header followed by the changes saved after every chunk, each
+/// prefixed by its length. A change cut short by a crash is dropped
+/// when resuming so the build continues from the previous one.
+struct BuildCheckpoint {
+    file: File,
+    /// The base layer as of the last save to find the changed nodes.
+    saved: Vec<BaseNode>,
+    /// Upper layers completed since the last save.
+    pending: Vec<usize>,
+}
+
+impl BuildCheckpoint {
+    /// Opens the checkpoint file or creates it if it doesn't exist.
+    /// Returns the file with the build state saved in it.
+    /// * `path`: File path of the checkpoint.
+    /// * `header`: Header of the records being built.
+    fn open(
+        path: &str,
+        header: &CheckpointHeader,
+    ) -> Result<(Self, SavedBuild), Error> {
+        let mut saved = SavedBuild {
+            layer: 0,
+            position: 0,
+            base_layer: vec![BaseNode::default(); header.len],
+            upper_layers: vec![UpperLayer::new(); header.layers],
+        };
+
+        if !Path::new(path).exists() {
+            let mut file = File::create(path)?;
+            Self::write_entry(&mut file, &bincode::serialize(header)?)?;
+            return Ok((Self::new(file, &saved), saved));
+        }
+
+        let bytes = read(path)?;
+        let (entry, mut offset) = match Self::read_entry(&bytes, 0) {
+            Some(entry) => entry,
+            None => return Err(Error::checkpoint_mismatch()),
+        };
+
+        let found: CheckpointHeader = bincode::deserialize(entry)?;
+        if found != *header {
+            return Err(Error::checkpoint_mismatch());
+        }
+
+        while let Some((entry, next)) = Self::read_entry(&bytes, offset) {
+            match bincode::deserialize(entry) {
+                Ok(delta) => saved.apply(delta)?,
+                Err(_) => break,
+            }
+
+            offset = next;
+        }
+
+        // Drop the incomplete change so the next ones follow the last
+        // complete change.
+        let file = OpenOptions::new().append(true).open(path)?;
+        file.set_len(offset as u64)?;
+        Ok((Self::new(file, &saved), saved))
+    }
+
+    /// Creates the writer of the checkpoint file.
+    /// * `file`: Checkpoint file opened for appending.
+    /// * `saved`: Build state already in the file.
+    fn new(file: File, saved: &SavedBuild) -> Self {
+        let saved = saved.base_layer.clone();
+        Self { file, saved, pending: vec![] }
+    }
+
+    /// Appends the changes of the index since the last save.
+    /// * `layer`: Position of the layer being built.
+    /// * `position`: The next vector ID to insert into the layer.
+    /// * `base_layer`: Base layer being built.
+    /// * `upper_layers`: Upper layers being built.
+    fn save(
+        &mut self,
+        layer: usize,
+        position: usize,
+        base_layer: &[RwLock<BaseNode>],
+        upper_layers: &[UpperLayer],
+    ) -> Result<(), Error> {
+        let nodes = base_layer.par_iter().zip(self.saved.par_iter_mut());
+        let base_nodes = nodes
+            .enumerate()
+            .filter_map(|(i, (node, saved))| {
+                let node = *node.read();
+                let changed = node.0 != saved.0;
+                *saved = node;
+                changed.then(|| (i.into(), node))
+            })
+            .collect();
+
+        let pending = self.pending.drain(..);
+        let upper_layers =
+            pending.map(|i| (i, upper_layers[i].clone())).collect();
+
+        let delta =
+            CheckpointDelta { layer, position, base_nodes, upper_layers };
+        Self::write_entry(&mut self.file, &bincode::serialize(&delta)?)
+    }
+
+    /// Appends the entry to the file prefixed by its length.
+    fn write_entry(file: &mut File, entry: &[u8]) -> Result<(), Error> {
+        let mut bytes = Vec::with_capacity(entry.len() + 8);
+        bytes.extend_from_slice(&(entry.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(entry);
+        file.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Returns the complete entry at the offset and the offset of
+    /// the next one if the entry was fully written.
+    fn read_entry(bytes: &[u8], offset: usize) -> Option<(&[u8], usize)> {
+        let start = offset.checked_add(8)?;
+        let len = bytes.get(offset..start)?;
+        let len = u64::from_le_bytes(len.try_into().ok()?) as usize;
+        let end = start.checked_add(len)?;
+        bytes.get(start..end).map(|entry| (entry, end))
+    }
+}
+
+/// The collection config serialized before the binary format.
+#[derive(Deserialize)]
+struct LegacyConfig {
+    ef_construction: usize,
+    ef_search: usize,
+    ml: f32,
+}
+
+/// The collection serialized before the binary format. The fields
+/// must stay in this order to decode the bincode layout. The index
+/// layers are decoded only to skip over them.
+#[derive(Deserialize)]
+struct LegacyCollection {
+    config: LegacyConfig,
+    data: HashMap<VectorID, Metadata>,
+    vectors: HashMap<VectorID, Vector>,
+    slots: Vec<VectorID>,
+    _base_layer: Vec<BaseNode>,
+    _upper_layers: Vec<Vec<UpperNode>>,
+    _count: usize,
+    dimension: usize,
+}
+
+/// The deleted record kept in the collection trash.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct TrashedRecord {
+    /// Vector ID of the record before the deletion.
+    id: VectorID,
+    record: Record,
+    /// Unix timestamp of the deletion in seconds.
+    deleted_at: u64,
+}
+
+impl TrashedRecord {
+    /// Returns true if the record was kept longer than the retention.
+    /// * `retention`: Seconds to keep the record. None if disabled.
+    fn is_expired(&self, retention: Option<usize>) -> bool {
+        match retention {
+            Some(retention) => {
+                unix_time().saturating_sub(self.deleted_at) >= retention as u64
+            }
+            None => true,
+        }
+    }
+}
+
+/// Returns the current Unix timestamp in seconds.
+fn unix_time() -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH);
+    now.map_or(0, |duration| duration.as_secs())
+}
+
+/// The summary of a collection encoded in the binary format.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FormatSummary {
+    /// Version of the binary format.
+    pub version: u8,
+    /// Size of the encoded collection in bytes.
+    pub size: usize,
+    /// Vector dimension of the collection.
+    pub dimension: usize,
+    /// Number of records in the collection.
+    pub records: usize,
+    /// Number of slots including the free ones.
+    pub slots: usize,
+    /// Number of slots freed by deletions.
+    pub free_slots: usize,
+    /// Number of nodes in each layer from the base layer up.
+    pub layers: Vec<usize>,
+    /// Vector ID of the index entry point.
+    pub entry_point: u32,
+    /// True if the collection is read-only.
+    pub read_only: bool,
+    /// Number of deleted records in the trash.
+    pub trash: usize,
+}
+
+/// The traversal statistics of a search in an index layer.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LayerExplain {
+    /// Index layer where 0 is the base layer.
+    pub layer: usize,
+    /// Vector ID of the nearest node when entering the layer.
+    pub entry_point: u32,
+    /// Number of nodes whose neighbors were expanded.
+    pub hops: usize,
+    /// Number of distances computed to the query vector.
+    /// Each computation is for a node visited for the first time.
+    pub distance_computations: usize,
+    /// Time spent traversing the layer.
+    pub duration: Duration,
+}
+
+/// The search results along with the statistics of the search.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SearchExplain {
+    /// Search results after filtering.
+    pub results: Vec<SearchResult>,
+    /// Layer statistics from the top layer down to the base layer.
+    /// Empty if the index layers weren't traversed.
+    pub layers: Vec<LayerExplain>,
+    /// True if the filter was served by the spatial index and
+    /// the vectors of the matches were compared directly.
+    pub exact: bool,
+    /// Number of candidates found before filtering.
+    pub candidates: usize,
+    /// Number of candidates the last index search looked for.
+    /// 0 if the index wasn't searched.
+    pub ef: usize,
+    /// Number of candidates pruned by the filter.
+    pub filtered: usize,
+    /// Time spent finding the candidates.
+    pub search_duration: Duration,
+    /// Time spent filtering the candidates into results.
+    pub filter_duration: Duration,
 }
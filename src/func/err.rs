@@ -3,6 +3,7 @@ use super::*;
 // Other error types.
 use bincode::ErrorKind as BincodeError;
 use pyo3::exceptions::PyValueError;
+use rayon::ThreadPoolBuildError;
 use sled::Error as SledError;
 use std::error::Error as StandardError;
 use std::io::Error as IOError;
@@ -24,12 +25,58 @@ impl Error {
 
     // Common collection errors.
 
+    /// Creates error when promoting a collection without a staged version.
+    pub fn staged_collection_not_found() -> Self {
+        let message = "The collection has no staged version.";
+        message.into()
+    }
+
+    /// Creates error when rolling back a collection that was
+    /// never promoted.
+    pub fn previous_collection_not_found() -> Self {
+        let message = "The collection has no previous version.";
+        message.into()
+    }
+
+    /// Creates error when another process has the database open.
+    pub fn database_locked(path: &str, pid: &str) -> Self {
+        let brief = "The database is in use by another process.";
+        let holder = if pid.is_empty() { "unknown" } else { pid };
+        let detail = format!("Process {holder} holds the lock on {path}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when the key is too long for the storage.
+    pub fn key_too_long(len: usize, max: usize) -> Self {
+        let brief = "The storage key is too long.";
+        let detail = format!("The max length is {max} bytes, found {len}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
     /// Creates error: The collection is not found.
     pub fn collection_not_found() -> Self {
         let message = "The collection is not found.";
         message.into()
     }
 
+    /// Creates error when a collection name is reserved for the
+    /// internal database keys.
+    pub fn reserved_collection_name() -> Self {
+        let brief = "Invalid collection name.";
+        let detail = "Names starting with a null character are reserved.";
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when a config parameter is out of range.
+    pub fn invalid_config(detail: &str) -> Self {
+        let brief = "Invalid collection config.";
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
     /// Creates error when the collection record limit is reached.
     pub fn collection_limit() -> Self {
         let max = u32::MAX;
@@ -39,14 +86,124 @@ impl Error {
         message.into()
     }
 
+    /// Creates error when the configured record limit is reached.
+    pub fn record_limit(max: usize) -> Self {
+        let brief = "The collection record limit is reached.";
+        let detail = format!("The max number of records is {max}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when the collection memory limit is reached.
+    pub fn memory_limit(max: usize) -> Self {
+        let brief = "The collection memory limit is reached.";
+        let detail = format!("The max memory usage is {max} bytes.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when modifying a read-only collection.
+    pub fn read_only() -> Self {
+        let message = "The collection is read-only.";
+        message.into()
+    }
+
+    /// Creates error when a search requests too many results.
+    pub fn result_limit(n: usize, max: usize) -> Self {
+        let brief = "The number of requested results is over the limit.";
+        let detail = format!("The max is {max}, found {n}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when the collection has no text index.
+    pub fn text_index_not_found() -> Self {
+        let message = "The collection has no text index.";
+        message.into()
+    }
+
+    /// Creates error when the collection has no spatial index.
+    pub fn geo_index_not_found() -> Self {
+        let message = "The collection has no geo index.";
+        message.into()
+    }
+
+    /// Creates error when the histogram interval isn't positive.
+    pub fn invalid_interval(interval: f32) -> Self {
+        let brief = "The histogram interval must be positive.";
+        let detail = format!("Found interval: {interval}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when the partition interval is zero.
+    pub fn invalid_partition_interval() -> Self {
+        let message = "The partition interval must be positive.";
+        message.into()
+    }
+
+    /// Creates error when a record has no timestamp to route it
+    /// to a partition by.
+    pub fn invalid_timestamp(field: &str) -> Self {
+        let brief = "Invalid record timestamp.";
+        let detail = format!("Expected an integer in the field: {field}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when the binary collection data is invalid.
+    pub fn invalid_format(detail: &str) -> Self {
+        let brief = "Invalid collection binary format.";
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
     // Common record errors.
 
+    /// Creates error when restoring a record that isn't in the trash.
+    pub fn trashed_record_not_found() -> Self {
+        let message = "The record is not found in the trash.";
+        message.into()
+    }
+
+    /// Creates error when a vector ID is too far past the last slot.
+    pub fn invalid_vector_id(id: u32, max: usize) -> Self {
+        let brief = "Invalid vector ID.";
+        let detail = format!("The max vector ID is {max}, found {id}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when the record is a duplicate of another.
+    pub fn duplicate_record(id: u32) -> Self {
+        let brief = "The record is a duplicate.";
+        let detail = format!("It matches the record with vector ID {id}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
     /// Creates error when vector record is not found.
     pub fn record_not_found() -> Self {
         let message = "The vector record is not found.";
         message.into()
     }
 
+    /// Creates error when a vector value is NaN or infinite.
+    pub fn non_finite_value(index: usize, value: f32) -> Self {
+        let brief = "Invalid vector value.";
+        let detail = format!("Found {value} at index {index}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when the vectors are from another model.
+    pub fn model_mismatch(found: &str, expected: &str) -> Self {
+        let brief = "Invalid embedding model.";
+        let detail = format!("Expected {expected}, found {found}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
     /// Creates error when getting vector with invalid dimension.
     pub fn invalid_dimension(found: usize, expected: usize) -> Self {
         let brief = "Invalid vector dimension.";
@@ -54,6 +211,51 @@ impl Error {
         let message = format!("{brief} {detail}");
         message.into()
     }
+
+    /// Creates error when a data field value can't be cast.
+    pub fn invalid_cast(field: &str, kind: MetadataKind) -> Self {
+        let brief = "Invalid field value.";
+        let detail = format!("The field {field} can't be cast to {kind:?}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when a rename would overwrite a data field.
+    pub fn field_exists(field: &str) -> Self {
+        let brief = "Field already exists.";
+        let detail = format!("The field {field} can't be renamed over.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when reading past versions without versioning.
+    pub fn history_disabled() -> Self {
+        let message = "The collection doesn't keep record versions.";
+        message.into()
+    }
+
+    /// Creates error when reading versions older than the retention.
+    pub fn history_expired(retention: usize) -> Self {
+        let brief = "The time is before the history retention.";
+        let detail = format!("The versions are kept for {retention} seconds.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
+
+    /// Creates error when resuming a build from the checkpoint of
+    /// different records or configuration.
+    pub fn checkpoint_mismatch() -> Self {
+        let message = "The build checkpoint doesn't match the records.";
+        message.into()
+    }
+
+    /// Creates error when the PCA dimension is out of range.
+    pub fn invalid_pca_dimension(found: usize, max: usize) -> Self {
+        let brief = "Invalid PCA dimension.";
+        let detail = format!("Expected 1 to {max}, found {found}.");
+        let message = format!("{brief} {detail}");
+        message.into()
+    }
 }
 
 // Quality of life conversions to Error type.
@@ -90,6 +292,12 @@ impl From<IOError> for Error {
     }
 }
 
+impl From<ThreadPoolBuildError> for Error {
+    fn from(err: ThreadPoolBuildError) -> Self {
+        Error(err.to_string())
+    }
+}
+
 impl From<Box<BincodeError>> for Error {
     fn from(err: Box<BincodeError>) -> Self {
         Error(err.to_string())
@@ -0,0 +1,165 @@
+use super::*;
+
+/// The condition to match the records by their data.
+/// Fields refer to the keys of the data object. If the data
+/// isn't an object, all of its fields are considered missing.
+///
+/// A field can be missing, null, or set to a value:
+/// * Missing: the data has no such key.
+/// * Null: the key exists with `Metadata::Null` as its value.
+/// * Set: the key exists with any other value.
+///
+/// Filters serialize to a JSON DSL with snake case names like
+/// `{"bool": {"must": [{"prefix": ["title", "vec"]}]}}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    /// Matches if the field exists and its value isn't null.
+    IsSet(String),
+    /// Matches if the field exists and its value is null.
+    /// Records missing the field don't match.
+    IsNull(String),
+    /// Matches if the field value equals the metadata.
+    /// Records missing the field don't match.
+    Equals(String, Metadata),
+    /// Matches if the field location is within the radius
+    /// of the center in meters. See `GeoPoint` for the format.
+    GeoRadius(String, GeoPoint, f32),
+    /// Matches if the field location is within the area.
+    GeoBoundingBox(String, GeoBoundingBox),
+    /// Matches if the numeric field value is within the range from
+    /// the inclusive start to the exclusive end. Values are compared
+    /// as `f64` so integers are only exact up to 2^53. Timestamps in
+    /// seconds or milliseconds are well within that.
+    Range(String, f64, f64),
+    /// Matches if the numeric field value compares to the number.
+    Compare(String, Comparison, f64),
+    /// Matches if the field value equals any of the values.
+    In(String, Vec<Metadata>),
+    /// Matches if the text field value starts with the prefix.
+    Prefix(String, String),
+    /// Matches if the text field value contains all terms of the query
+    /// after the same tokenization as the text index.
+    TextMatch(String, String),
+    /// Matches if the inner filter doesn't match.
+    Not(Box<Filter>),
+    /// Combines the filters. Matches if all `must` filters match, no
+    /// `must_not` filter matches, and at least one `should` filter
+    /// matches unless there are none.
+    Bool {
+        /// Filters that must all match.
+        #[serde(default)]
+        must: Vec<Filter>,
+        /// Filters of which at least one must match.
+        #[serde(default)]
+        should: Vec<Filter>,
+        /// Filters that must not match.
+        #[serde(default)]
+        must_not: Vec<Filter>,
+    },
+}
+
+/// The operator to compare a numeric field value to a number.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    /// The value is greater than the number.
+    Greater,
+    /// The value is greater than or equal to the number.
+    GreaterOrEqual,
+    /// The value is less than the number.
+    Less,
+    /// The value is less than or equal to the number.
+    LessOrEqual,
+}
+
+impl Filter {
+    /// Returns true if the record data matches the filter.
+    /// * `data`: Data of the record.
+    pub fn matches(&self, data: &Metadata) -> bool {
+        match self {
+            Filter::IsSet(field) => {
+                matches!(data.field(field), Some(v) if !v.is_null())
+            }
+            Filter::IsNull(field) => {
+                matches!(data.field(field), Some(v) if v.is_null())
+            }
+            Filter::Equals(field, value) => data.field(field) == Some(value),
+            Filter::GeoRadius(field, center, radius) => {
+                let point = data.field(field).and_then(GeoPoint::from_metadata);
+                matches!(point, Some(p) if p.distance(center) <= *radius)
+            }
+            Filter::GeoBoundingBox(field, area) => {
+                let point = data.field(field).and_then(GeoPoint::from_metadata);
+                matches!(point, Some(p) if area.contains(&p))
+            }
+            Filter::Range(field, start, end) => {
+                let value = match number(data, field) {
+                    Some(value) => value,
+                    None => return false,
+                };
+
+                *start <= value && value < *end
+            }
+            Filter::Compare(field, comparison, other) => {
+                let value = match number(data, field) {
+                    Some(value) => value,
+                    None => return false,
+                };
+
+                match comparison {
+                    Comparison::Greater => value > *other,
+                    Comparison::GreaterOrEqual => value >= *other,
+                    Comparison::Less => value < *other,
+                    Comparison::LessOrEqual => value <= *other,
+                }
+            }
+            Filter::In(field, values) => {
+                matches!(data.field(field), Some(v) if values.contains(v))
+            }
+            Filter::Prefix(field, prefix) => match data.field(field) {
+                Some(Metadata::Text(text)) => text.starts_with(prefix),
+                _ => false,
+            },
+            Filter::TextMatch(field, query) => match data.field(field) {
+                Some(Metadata::Text(text)) => {
+                    let terms = tokenize(text);
+                    tokenize(query).iter().all(|term| terms.contains(term))
+                }
+                _ => false,
+            },
+            Filter::Not(filter) => !filter.matches(data),
+            Filter::Bool { must, should, must_not } => {
+                must.iter().all(|filter| filter.matches(data))
+                    && !must_not.iter().any(|filter| filter.matches(data))
+                    && (should.is_empty()
+                        || should.iter().any(|filter| filter.matches(data)))
+            }
+        }
+    }
+}
+
+/// Returns the numeric field value as `f64`. Integers above 2^53
+/// are rounded to the nearest `f64`.
+fn number(data: &Metadata, field: &str) -> Option<f64> {
+    match data.field(field) {
+        Some(Metadata::Integer(int)) => Some(*int as f64),
+        Some(Metadata::Float(float)) => Some(*float as f64),
+        _ => None,
+    }
+}
+
+/// The way to search the nearest neighbors matching a filter.
+/// Each strategy is the fastest for a range of filter selectivity.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterStrategy {
+    /// Filters the candidates of a regular index search. Best when
+    /// most records match.
+    PostFilter,
+    /// Grows the index search up to the max EF until enough
+    /// candidates match. Best for moderately selective filters.
+    Oversample(usize),
+    /// Compares the vectors of all matching records directly.
+    /// Best when few records match.
+    Exact,
+}
@@ -0,0 +1,254 @@
+use super::*;
+
+/// Mean radius of the Earth in meters.
+const EARTH_RADIUS: f32 = 6_371_000.0;
+/// Size of the spatial index grid cells in degrees.
+const CELL_SIZE: f32 = 1.0;
+
+/// The geographic location in degrees.
+/// In the record data, a location is an object with numeric
+/// `lat` and `lon` fields like `{"lat": 52.52, "lon": 13.40}`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct GeoPoint {
+    /// Latitude between -90 and 90 degrees.
+    pub lat: f32,
+    /// Longitude between -180 and 180 degrees.
+    pub lon: f32,
+}
+
+impl GeoPoint {
+    /// Creates a new location from the coordinates.
+    /// * `lat`: Latitude in degrees.
+    /// * `lon`: Longitude in degrees.
+    pub fn new(lat: f32, lon: f32) -> Self {
+        Self { lat, lon }
+    }
+
+    /// Returns the location stored in the metadata if it's an
+    /// object with valid numeric `lat` and `lon` fields.
+    /// * `data`: Metadata to read the location from.
+    pub fn from_metadata(data: &Metadata) -> Option<Self> {
+        let obj = match data {
+            Metadata::Object(obj) => obj,
+            _ => return None,
+        };
+
+        let coordinate = |key: &str| match obj.get(key)? {
+            Metadata::Float(value) => Some(*value),
+            Metadata::Integer(value) => Some(*value as f32),
+            _ => None,
+        };
+
+        let point = Self::new(coordinate("lat")?, coordinate("lon")?);
+        let valid = point.lat.abs() <= 90.0 && point.lon.abs() <= 180.0;
+        valid.then_some(point)
+    }
+
+    /// Returns the great-circle distance to the other location
+    /// in meters using the haversine formula.
+    /// * `other`: Location to measure the distance to.
+    pub fn distance(&self, other: &GeoPoint) -> f32 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2)
+            + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+    }
+
+    /// Returns the grid cell of the spatial index containing the location.
+    fn cell(&self) -> (i32, i32) {
+        let lat = (self.lat / CELL_SIZE).floor() as i32;
+        let lon = (self.lon / CELL_SIZE).floor() as i32;
+        (lat, lon)
+    }
+}
+
+/// The rectangular area between two corner locations.
+/// If the west edge is east of the east edge, the area crosses
+/// the antimeridian like from 170 to -170 degrees longitude.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct GeoBoundingBox {
+    /// North-west corner of the area.
+    pub top_left: GeoPoint,
+    /// South-east corner of the area.
+    pub bottom_right: GeoPoint,
+}
+
+impl GeoBoundingBox {
+    /// Creates a new area from the corner locations.
+    /// * `top_left`: North-west corner of the area.
+    /// * `bottom_right`: South-east corner of the area.
+    pub fn new(top_left: GeoPoint, bottom_right: GeoPoint) -> Self {
+        Self { top_left, bottom_right }
+    }
+
+    /// Returns the smallest area containing the circle.
+    /// * `center`: Center of the circle.
+    /// * `radius`: Radius of the circle in meters.
+    pub fn around(center: &GeoPoint, radius: f32) -> Self {
+        let dlat = (radius / EARTH_RADIUS).to_degrees();
+        let top = (center.lat + dlat).min(90.0);
+        let bottom = (center.lat - dlat).max(-90.0);
+
+        // Near the poles, the circle can span all longitudes.
+        let max_lat = top.abs().max(bottom.abs());
+        let dlon = dlat / max_lat.to_radians().cos();
+        let (left, right) = match dlon.is_finite() && dlon < 180.0 {
+            true => (center.lon - dlon, center.lon + dlon),
+            false => (-180.0, 180.0),
+        };
+
+        // Wrap the edges past the antimeridian to the other side.
+        let left = if left < -180.0 { left + 360.0 } else { left };
+        let right = if right > 180.0 { right - 360.0 } else { right };
+
+        let top_left = GeoPoint::new(top, left);
+        let bottom_right = GeoPoint::new(bottom, right);
+        Self::new(top_left, bottom_right)
+    }
+
+    /// Returns true if the location is within the area.
+    /// * `point`: Location to check.
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        let (top_left, bottom_right) = (&self.top_left, &self.bottom_right);
+        let lat = point.lat <= top_left.lat && point.lat >= bottom_right.lat;
+        let mut ranges = self.lon_ranges().into_iter().flatten();
+        let lon =
+            ranges.any(|(west, east)| point.lon >= west && point.lon <= east);
+
+        lat && lon
+    }
+
+    /// Returns the west and east edges of the area. An area crossing
+    /// the antimeridian is split into the ranges on both sides of it.
+    pub fn lon_ranges(&self) -> [Option<(f32, f32)>; 2] {
+        let (west, east) = (self.top_left.lon, self.bottom_right.lon);
+        match west <= east {
+            true => [Some((west, east)), None],
+            false => [Some((west, 180.0)), Some((-180.0, east))],
+        }
+    }
+}
+
+/// The spatial index over the location of a data field.
+/// Locations are grouped into grid cells so that area queries
+/// only check the records in the cells overlapping the area.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GeoIndex {
+    field: String,
+    points: HashMap<VectorID, GeoPoint>,
+    cells: HashMap<(i32, i32), Vec<VectorID>>,
+}
+
+impl GeoIndex {
+    /// Creates an empty spatial index over the data field.
+    /// * `field`: Name of the data object field storing the location.
+    pub fn new(field: &str) -> Self {
+        Self { field: field.to_string(), ..Default::default() }
+    }
+
+    /// Returns the name of the indexed data field.
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the estimated memory usage of the index in bytes.
+    /// Each point is listed in exactly one cell.
+    pub fn memory_usage(&self) -> usize {
+        let cells = self.points.len() * size_of::<VectorID>();
+        map_size(&self.points) + map_size(&self.cells) + cells
+    }
+
+    /// Indexes the location of the record data if it has one.
+    /// * `id`: Vector ID of the record.
+    /// * `data`: Data of the record.
+    pub fn insert(&mut self, id: &VectorID, data: &Metadata) {
+        let point = data.field(&self.field);
+        if let Some(point) = point.and_then(GeoPoint::from_metadata) {
+            self.cells.entry(point.cell()).or_default().push(*id);
+            self.points.insert(*id, point);
+        }
+    }
+
+    /// Removes the record from the index.
+    /// * `id`: Vector ID of the record.
+    pub fn remove(&mut self, id: &VectorID) {
+        let cell = match self.points.remove(id) {
+            Some(point) => point.cell(),
+            None => return,
+        };
+
+        if let Some(ids) = self.cells.get_mut(&cell) {
+            ids.retain(|other| other != id);
+            if ids.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// Returns the records located within the area.
+    /// * `area`: Area to search in.
+    pub fn within_box(&self, area: &GeoBoundingBox) -> Vec<VectorID> {
+        let (top, bottom) = (area.top_left.lat, area.bottom_right.lat);
+
+        // Both ranges of an area crossing the antimeridian can
+        // overlap the same cells if the edges are in one cell.
+        let mut visited = HashSet::new();
+        let mut ids = vec![];
+        for (west, east) in area.lon_ranges().into_iter().flatten() {
+            let (min_lat, min_lon) = GeoPoint::new(bottom, west).cell();
+            let (max_lat, max_lon) = GeoPoint::new(top, east).cell();
+
+            for lat in min_lat..=max_lat {
+                for lon in min_lon..=max_lon {
+                    if !visited.insert((lat, lon)) {
+                        continue;
+                    }
+
+                    let cell = match self.cells.get(&(lat, lon)) {
+                        Some(cell) => cell,
+                        None => continue,
+                    };
+
+                    let is_within =
+                        |id: &&VectorID| area.contains(&self.points[id]);
+                    ids.extend(cell.iter().filter(is_within));
+                }
+            }
+        }
+
+        ids
+    }
+
+    /// Returns the records located within the radius of the center.
+    /// * `center`: Center of the circle.
+    /// * `radius`: Radius of the circle in meters.
+    pub fn within_radius(
+        &self,
+        center: &GeoPoint,
+        radius: f32,
+    ) -> Vec<VectorID> {
+        let area = GeoBoundingBox::around(center, radius);
+        let mut ids = self.within_box(&area);
+        ids.retain(|id| self.points[id].distance(center) <= radius);
+        ids
+    }
+
+    /// Returns the records nearest to the location with their
+    /// distance in meters sorted by ascending distance.
+    /// * `center`: Location to measure the distance from.
+    /// * `n`: Number of records to return.
+    pub fn nearest(&self, center: &GeoPoint, n: usize) -> Vec<(VectorID, f32)> {
+        let mut results: Vec<(VectorID, f32)> = self
+            .points
+            .iter()
+            .map(|(id, point)| (*id, point.distance(center)))
+            .collect();
+
+        results.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        results.truncate(n);
+        results
+    }
+}
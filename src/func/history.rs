@@ -0,0 +1,101 @@
+use super::*;
+
+/// A past version of a record and the time range it was current.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordVersion {
+    /// Vector ID of the record.
+    pub id: VectorID,
+    /// Vector and data of the record in this version.
+    pub record: Record,
+    /// Unix timestamp in seconds when the version was written.
+    pub from: u64,
+    /// Unix timestamp in seconds when the version was replaced.
+    pub to: u64,
+}
+
+impl RecordVersion {
+    /// Returns true if the version was current at the timestamp.
+    /// * `timestamp`: Unix timestamp in seconds.
+    pub fn is_current_at(&self, timestamp: u64) -> bool {
+        self.from <= timestamp && timestamp < self.to
+    }
+}
+
+/// The history of the records to read the collection as of a
+/// past time. Records written before the history was enabled are
+/// treated as existing since the Unix epoch.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RecordHistory {
+    created: HashMap<VectorID, u64>,
+    versions: Vec<RecordVersion>,
+    // Heap bytes of the records in the past versions.
+    heap_size: usize,
+}
+
+impl RecordHistory {
+    /// Creates the history from the decoded write times and versions.
+    /// * `created`: Write times of the current versions.
+    /// * `versions`: Past versions of the records.
+    pub fn new(
+        created: HashMap<VectorID, u64>,
+        versions: Vec<RecordVersion>,
+    ) -> Self {
+        let size = |version: &RecordVersion| {
+            record_size(&version.record.vector, &version.record.data)
+        };
+
+        let heap_size = versions.iter().map(size).sum();
+        Self { created, versions, heap_size }
+    }
+
+    /// Records the time the current version of the record was written.
+    /// * `id`: Vector ID of the record.
+    /// * `timestamp`: Unix timestamp in seconds.
+    pub fn insert(&mut self, id: &VectorID, timestamp: u64) {
+        self.created.insert(*id, timestamp);
+    }
+
+    /// Keeps the current version of the record as a past version
+    /// before it's replaced or removed.
+    /// * `id`: Vector ID of the record.
+    /// * `record`: Current version of the record.
+    /// * `timestamp`: Unix timestamp in seconds.
+    pub fn retire(&mut self, id: &VectorID, record: Record, timestamp: u64) {
+        let from = self.created.remove(id).unwrap_or(0);
+        self.heap_size += record_size(&record.vector, &record.data);
+        let version = RecordVersion { id: *id, record, from, to: timestamp };
+        self.versions.push(version);
+    }
+
+    /// Drops the past versions replaced before the timestamp.
+    /// * `timestamp`: Unix timestamp in seconds.
+    pub fn expire(&mut self, timestamp: u64) {
+        // The versions are in the order they were replaced.
+        let expired = self.versions.partition_point(|v| v.to < timestamp);
+        for version in self.versions.drain(..expired) {
+            let record = &version.record;
+            self.heap_size -= record_size(&record.vector, &record.data);
+        }
+    }
+
+    /// Returns the time the current version of the record was written.
+    /// * `id`: Vector ID of the record.
+    pub fn created(&self, id: &VectorID) -> u64 {
+        self.created.get(id).copied().unwrap_or(0)
+    }
+
+    /// Returns the past versions in the order they were replaced.
+    pub fn versions(&self) -> &[RecordVersion] {
+        &self.versions
+    }
+
+    /// Returns the write times of the current versions.
+    pub fn created_times(&self) -> &HashMap<VectorID, u64> {
+        &self.created
+    }
+
+    /// Returns the estimated memory usage of the history in bytes.
+    pub fn memory_usage(&self) -> usize {
+        map_size(&self.created) + vec_size(&self.versions) + self.heap_size
+    }
+}
@@ -13,6 +13,43 @@ pub enum Metadata {
     Array(Vec<Metadata>),
     /// A map of string and metadata pairs. The most common type.
     Object(HashMap<String, Metadata>),
+    /// An explicit null value such as Python's None.
+    Null,
+}
+
+impl Metadata {
+    /// Returns the value of the field if the metadata is an object.
+    /// * `field`: Name of the object field.
+    pub fn field(&self, field: &str) -> Option<&Metadata> {
+        match self {
+            Metadata::Object(obj) => obj.get(field),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the metadata is null.
+    pub fn is_null(&self) -> bool {
+        matches!(self, Metadata::Null)
+    }
+
+    /// Returns the estimated heap size of the metadata in bytes.
+    pub(crate) fn heap_size(&self) -> usize {
+        match self {
+            Metadata::Text(text) => text.capacity(),
+            Metadata::Integer(_) | Metadata::Float(_) | Metadata::Null => 0,
+            Metadata::Array(arr) => {
+                let items: usize = arr.iter().map(Metadata::heap_size).sum();
+                vec_size(arr) + items
+            }
+            Metadata::Object(obj) => {
+                let entry_size =
+                    |(k, v): (&String, &Metadata)| k.capacity() + v.heap_size();
+
+                let entries: usize = obj.iter().map(entry_size).sum();
+                map_size(obj) + entries
+            }
+        }
+    }
 }
 
 impl From<usize> for Metadata {
@@ -75,6 +112,11 @@ where
 // Python object into the Metadata enum.
 impl From<&PyAny> for Metadata {
     fn from(value: &PyAny) -> Self {
+        // Extract None as null.
+        if value.is_none() {
+            return Metadata::Null;
+        }
+
         // Extract string.
         if let Ok(text) = value.extract::<String>() {
             return Metadata::Text(text);
@@ -135,6 +177,7 @@ impl IntoPy<Py<PyAny>> for Metadata {
             Metadata::Float(float) => float.into_py(py),
             Metadata::Array(arr) => list_converter(arr),
             Metadata::Object(obj) => dict_converter(obj),
+            Metadata::Null => py.None(),
         }
     }
 }
@@ -0,0 +1,119 @@
+use super::*;
+
+/// The type of metadata to cast a field value to.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetadataKind {
+    /// Text from numbers like `42` or `0.5`.
+    Text,
+    /// Integer from text or from non-negative floats by truncation.
+    Integer,
+    /// Float from text or integers.
+    Float,
+}
+
+/// A change to the fields of the record data objects. Records whose
+/// data isn't an object are left as is.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Migration {
+    /// Renames the field from the first name to the second one.
+    /// Records missing the field are left as is. Records that
+    /// already have the second field fail the migration.
+    Rename(String, String),
+    /// Casts the field value to the type. Missing, null, and values
+    /// of the type are left as is. Other values must be convertible.
+    Cast(String, MetadataKind),
+    /// Sets the field to the value if the field is missing.
+    SetDefault(String, Metadata),
+    /// Removes the field.
+    Remove(String),
+}
+
+impl Migration {
+    /// Returns the migrated data or None if the data is unchanged.
+    /// * `data`: Data of a record.
+    pub fn apply(&self, data: &Metadata) -> Result<Option<Metadata>, Error> {
+        let fields = match data {
+            Metadata::Object(fields) => fields,
+            _ => return Ok(None),
+        };
+
+        let mut fields = fields.clone();
+        match self {
+            Migration::Rename(from, to) => {
+                if from == to || !fields.contains_key(from) {
+                    return Ok(None);
+                }
+
+                // Renaming onto an existing field would lose its value.
+                if fields.contains_key(to) {
+                    return Err(Error::field_exists(to));
+                }
+
+                let value = fields.remove(from).unwrap();
+                fields.insert(to.clone(), value)
+            }
+            Migration::Cast(field, kind) => match fields.get(field) {
+                Some(value) if !value.is_null() && !is_kind(value, *kind) => {
+                    let value = cast(field, value, *kind)?;
+                    fields.insert(field.clone(), value)
+                }
+                _ => return Ok(None),
+            },
+            Migration::SetDefault(field, value) => {
+                if fields.contains_key(field) {
+                    return Ok(None);
+                }
+
+                fields.insert(field.clone(), value.clone())
+            }
+            Migration::Remove(field) => match fields.remove(field) {
+                Some(value) => Some(value),
+                None => return Ok(None),
+            },
+        };
+
+        Ok(Some(Metadata::Object(fields)))
+    }
+}
+
+/// Returns true if the value is already of the metadata type.
+fn is_kind(value: &Metadata, kind: MetadataKind) -> bool {
+    matches!(
+        (value, kind),
+        (Metadata::Text(_), MetadataKind::Text)
+            | (Metadata::Integer(_), MetadataKind::Integer)
+            | (Metadata::Float(_), MetadataKind::Float)
+    )
+}
+
+/// Converts the field value to the metadata type.
+fn cast(
+    field: &str,
+    value: &Metadata,
+    kind: MetadataKind,
+) -> Result<Metadata, Error> {
+    let casted = match (value, kind) {
+        (Metadata::Integer(int), MetadataKind::Text) => {
+            Some(int.to_string().into())
+        }
+        (Metadata::Float(float), MetadataKind::Text) => {
+            Some(float.to_string().into())
+        }
+        (Metadata::Text(text), MetadataKind::Integer) => {
+            text.trim().parse::<usize>().ok().map(Metadata::Integer)
+        }
+        (Metadata::Text(text), MetadataKind::Float) => {
+            text.trim().parse::<f32>().ok().map(Metadata::Float)
+        }
+        (Metadata::Integer(int), MetadataKind::Float) => {
+            Some(Metadata::Float(*int as f32))
+        }
+        (Metadata::Float(float), MetadataKind::Integer) => {
+            let valid = float.is_finite() && *float >= 0.0;
+            valid.then_some(Metadata::Integer(*float as usize))
+        }
+        _ => None,
+    };
+
+    casted.ok_or_else(|| Error::invalid_cast(field, kind))
+}
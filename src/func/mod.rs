@@ -1,18 +1,43 @@
+/// Facet counts and histograms over the record data.
+pub mod aggregate;
 /// The collection of vectors and their data.
 pub mod collection;
 /// Error types for the database.
 pub mod err;
+/// Filters to match the records by their data.
+pub mod filter;
+/// Geographic locations and the spatial index.
+pub mod geo;
+/// Past versions of the records for reads as of a time.
+pub mod history;
 /// Types for the metadata.
 pub mod metadata;
+/// Changes to the data fields of all records.
+pub mod migration;
+/// Collections partitioned by the time of their records.
+pub mod partition;
+/// Full-text search over the record data.
+pub mod text;
+/// Transformations of the vectors before indexing.
+pub mod transform;
 /// Types for the vectors.
 pub mod vector;
 
 // Internal modules.
-mod utils;
+mod binary;
+pub(crate) mod utils;
 
+use aggregate::*;
+use binary::*;
 use collection::*;
 use err::*;
+use filter::{Filter, FilterStrategy};
+use geo::*;
+use history::*;
 use metadata::*;
+use migration::*;
+use text::*;
+use transform::*;
 use utils::*;
 use vector::*;
 
@@ -20,13 +45,25 @@ use vector::*;
 use ordered_float::OrderedFloat;
 use parking_lot::*;
 use pyo3::prelude::*;
-use rand::random;
+use rand::rngs::StdRng;
+use rand::{random, Rng, SeedableRng};
 use rayon::iter::*;
-use serde::{Deserialize, Serialize};
+use rayon::ThreadPoolBuilder;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_big_array::BigArray;
+use std::borrow::Cow;
 use std::cmp::*;
-use std::collections::{BinaryHeap, HashMap};
-use std::ops::{Deref, Index};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::fs::{read, remove_file, rename, write, File, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::iter::once;
+use std::mem::{replace, size_of, take};
+use std::ops::{Deref, Index, Range};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 // This code is inspired by the HNSW implementation in the
 // Instant Distance library and modified to fit the needs
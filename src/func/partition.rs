@@ -0,0 +1,157 @@
+use super::*;
+
+/// The collection split into partitions by the timestamp of its
+/// records like daily or monthly partitions. Each partition is a
+/// separate collection so dropping old records by time is cheap.
+///
+/// Timestamps are integers in the data object field like seconds
+/// since the Unix epoch. A partition covers the timestamps from its
+/// key, a multiple of the interval, up to the key of the next one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PartitionedCollection {
+    config: Config,
+    field: String,
+    interval: usize,
+    partitions: BTreeMap<usize, Collection>,
+}
+
+impl PartitionedCollection {
+    /// Creates an empty partitioned collection. For daily partitions
+    /// of timestamps in seconds, the interval is `86400`.
+    /// * `config`: Configuration of each partition.
+    /// * `field`: Name of the data object field storing the timestamp.
+    /// * `interval`: Timestamp range of each partition like `86400`.
+    pub fn new(
+        config: &Config,
+        field: &str,
+        interval: usize,
+    ) -> Result<Self, Error> {
+        if interval == 0 {
+            return Err(Error::invalid_partition_interval());
+        }
+
+        config.validate()?;
+        Ok(Self {
+            config: *config,
+            field: field.to_string(),
+            interval,
+            partitions: BTreeMap::new(),
+        })
+    }
+
+    /// Inserts the record into the partition of its timestamp and
+    /// creates the partition if it doesn't exist yet. Returns the
+    /// partition key and the vector ID in that partition.
+    /// * `record`: Vector record with the timestamp in its data.
+    pub fn insert(
+        &mut self,
+        record: &Record,
+    ) -> Result<(usize, VectorID), Error> {
+        let timestamp = match record.data.field(&self.field) {
+            Some(Metadata::Integer(int)) => *int,
+            _ => return Err(Error::invalid_timestamp(&self.field)),
+        };
+
+        let key = self.partition_key(timestamp);
+        let config = self.config;
+        let partition = self
+            .partitions
+            .entry(key)
+            .or_insert_with(|| Collection::new(&config));
+
+        let id = partition.insert(record)?;
+        Ok((key, id))
+    }
+
+    /// Searches the partitions overlapping the time range for the
+    /// nearest neighbors and merges the results by distance. Each
+    /// result is paired with the key of the partition it's from.
+    /// * `vector`: Vector to search.
+    /// * `n`: Number of neighbors to return.
+    /// * `start`: Inclusive start of the time range if bounded.
+    /// * `end`: Exclusive end of the time range if bounded.
+    pub fn search(
+        &self,
+        vector: &Vector,
+        n: usize,
+        start: Option<usize>,
+        end: Option<usize>,
+    ) -> Result<Vec<(usize, SearchResult)>, Error> {
+        let first = start.map_or(0, |start| self.partition_key(start));
+        let last = end.unwrap_or(usize::MAX);
+        if first >= last {
+            return Ok(vec![]);
+        }
+
+        let start = start.unwrap_or(0);
+        let filter =
+            Filter::Range(self.field.clone(), start as f64, last as f64);
+
+        // Only the partitions at the edges of the range can contain
+        // records outside of it so only those are filtered.
+        let search = |(key, partition): (&usize, &Collection)| {
+            let covered =
+                *key >= start && key.saturating_add(self.interval) <= last;
+            let results = match covered {
+                true => partition.search(vector, n)?,
+                false => partition.search_with_filter(vector, n, &filter)?,
+            };
+
+            let labeled = results.into_iter().map(|res| (*key, res));
+            Ok(labeled.collect::<Vec<_>>())
+        };
+
+        let partitions: Vec<_> = self.partitions.range(first..last).collect();
+        let results: Result<Vec<_>, Error> =
+            partitions.into_par_iter().map(search).collect();
+        let mut results: Vec<_> = results?.into_iter().flatten().collect();
+
+        results.sort_by(|a, b| a.1.distance.total_cmp(&b.1.distance));
+        results.truncate(n);
+        Ok(results)
+    }
+
+    /// Drops the partitions whose time range ends before the timestamp.
+    /// The partition containing the timestamp is kept.
+    /// Returns the number of dropped partitions.
+    /// * `timestamp`: Timestamp of the oldest records to keep.
+    pub fn drop_before(&mut self, timestamp: usize) -> usize {
+        let key = self.partition_key(timestamp);
+        let kept = self.partitions.split_off(&key);
+        let dropped = replace(&mut self.partitions, kept);
+        dropped.len()
+    }
+
+    /// Returns the key of the partition covering the timestamp.
+    /// * `timestamp`: Timestamp of a record.
+    pub fn partition_key(&self, timestamp: usize) -> usize {
+        timestamp - timestamp % self.interval
+    }
+
+    /// Returns the keys of the partitions from the oldest.
+    pub fn partitions(&self) -> Vec<usize> {
+        self.partitions.keys().copied().collect()
+    }
+
+    /// Returns the partition with the key if it exists.
+    /// * `key`: Partition key returned by `insert`.
+    pub fn partition(&self, key: usize) -> Option<&Collection> {
+        self.partitions.get(&key)
+    }
+
+    /// Returns the mutable partition with the key if it exists.
+    /// * `key`: Partition key returned by `insert`.
+    pub fn partition_mut(&mut self, key: usize) -> Option<&mut Collection> {
+        self.partitions.get_mut(&key)
+    }
+
+    /// Returns the number of records in all partitions.
+    pub fn len(&self) -> usize {
+        self.partitions.values().map(|p| p.len()).sum()
+    }
+
+    /// Returns true if the partitions have no records.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
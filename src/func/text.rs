@@ -0,0 +1,193 @@
+use super::*;
+
+/// BM25 term frequency saturation parameter.
+const K1: f32 = 1.2;
+/// BM25 document length normalization parameter.
+const B: f32 = 0.75;
+
+/// The indexed terms of a record's text.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Document {
+    length: usize,
+    terms: Vec<String>,
+}
+
+/// The inverted index for full-text search over the record data.
+/// Records are ranked by relevance using the BM25 algorithm.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TextIndex {
+    fields: Vec<String>,
+    postings: HashMap<String, HashMap<VectorID, usize>>,
+    documents: HashMap<VectorID, Document>,
+    total_length: usize,
+    // Heap bytes of the terms and the posting entries.
+    heap_size: usize,
+}
+
+impl TextIndex {
+    /// Creates an empty text index over the data fields.
+    /// * `fields`: Names of the data object fields to index.
+    pub fn new(fields: &[String]) -> Self {
+        Self { fields: fields.to_vec(), ..Default::default() }
+    }
+
+    /// Returns the names of the indexed data fields.
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Indexes the text of the record data.
+    /// * `id`: Vector ID of the record.
+    /// * `data`: Data of the record.
+    pub fn insert(&mut self, id: &VectorID, data: &Metadata) {
+        let mut texts = vec![];
+        match data {
+            Metadata::Object(obj) => {
+                let values = self.fields.iter().filter_map(|f| obj.get(f));
+                values.for_each(|value| collect_text(value, &mut texts));
+            }
+            _ => collect_text(data, &mut texts),
+        }
+
+        let terms: Vec<String> =
+            texts.iter().flat_map(|text| tokenize(text)).collect();
+        if terms.is_empty() {
+            return;
+        }
+
+        let mut frequencies: HashMap<String, usize> = HashMap::new();
+        for term in terms.iter() {
+            *frequencies.entry(term.clone()).or_default() += 1;
+        }
+
+        for (term, frequency) in frequencies.iter() {
+            if !self.postings.contains_key(term) {
+                self.heap_size += term.len();
+            }
+
+            let posting = self.postings.entry(term.clone()).or_default();
+            posting.insert(*id, *frequency);
+        }
+
+        let length = terms.len();
+        let terms: Vec<String> = frequencies.into_keys().collect();
+        self.heap_size += document_size(&terms);
+        self.documents.insert(*id, Document { length, terms });
+        self.total_length += length;
+    }
+
+    /// Removes the record from the index.
+    /// * `id`: Vector ID of the record.
+    pub fn remove(&mut self, id: &VectorID) {
+        let document = match self.documents.remove(id) {
+            Some(document) => document,
+            None => return,
+        };
+
+        for term in document.terms.iter() {
+            if let Some(posting) = self.postings.get_mut(term) {
+                posting.remove(id);
+                if posting.is_empty() {
+                    self.postings.remove(term);
+                    self.heap_size -= term.len();
+                }
+            }
+        }
+
+        self.heap_size -= document_size(&document.terms);
+        self.total_length -= document.length;
+    }
+
+    /// Returns the estimated memory usage of the index in bytes.
+    pub fn memory_usage(&self) -> usize {
+        map_size(&self.postings) + map_size(&self.documents) + self.heap_size
+    }
+
+    /// Returns the most relevant records for the query
+    /// with their BM25 score sorted by descending score.
+    /// * `query`: Text to search for.
+    /// * `n`: Number of records to return.
+    pub fn search(&self, query: &str, n: usize) -> Vec<(VectorID, f32)> {
+        if self.documents.is_empty() {
+            return vec![];
+        }
+
+        let count = self.documents.len() as f32;
+        let average_length = self.total_length as f32 / count;
+
+        let mut scores: HashMap<VectorID, f32> = HashMap::new();
+        for term in tokenize(query) {
+            let posting = match self.postings.get(&term) {
+                Some(posting) => posting,
+                None => continue,
+            };
+
+            let df = posting.len() as f32;
+            let idf = ((count - df + 0.5) / (df + 0.5) + 1.0).ln();
+
+            for (id, frequency) in posting.iter() {
+                let tf = *frequency as f32;
+                let length = self.documents[id].length as f32;
+                let norm = K1 * (1.0 - B + B * length / average_length);
+                let score = idf * tf * (K1 + 1.0) / (tf + norm);
+                *scores.entry(*id).or_default() += score;
+            }
+        }
+
+        let mut results: Vec<(VectorID, f32)> = scores.into_iter().collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        results.truncate(n);
+        results
+    }
+}
+
+/// Returns the estimated heap size of the document terms and
+/// their entries in the postings of the terms.
+fn document_size(terms: &[String]) -> usize {
+    let entry = size_of::<String>() + size_of::<(VectorID, usize)>() + 1;
+    terms.iter().map(|term| entry + term.len()).sum()
+}
+
+/// Collects the text values from the metadata recursively.
+fn collect_text<'a>(data: &'a Metadata, texts: &mut Vec<&'a str>) {
+    match data {
+        Metadata::Text(text) => texts.push(text),
+        Metadata::Array(arr) => {
+            arr.iter().for_each(|value| collect_text(value, texts))
+        }
+        _ => {}
+    }
+}
+
+/// Splits the text into lowercase and stemmed terms.
+/// * `text`: Text to tokenize.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| stem(&token.to_lowercase()))
+        .collect()
+}
+
+/// Strips common English suffixes from the term so that
+/// inflected words like "searching" match "search".
+fn stem(term: &str) -> String {
+    if let Some(stem) = term.strip_suffix("ies") {
+        if stem.len() >= 2 {
+            return format!("{stem}y");
+        }
+    }
+
+    for suffix in ["ing", "ed", "ly"] {
+        match term.strip_suffix(suffix) {
+            Some(stem) if stem.len() >= 3 => return stem.to_string(),
+            _ => continue,
+        }
+    }
+
+    // Avoid stripping words like "class", "status", or "analysis".
+    let keep = ["ss", "us", "is"].iter().any(|end| term.ends_with(end));
+    match term.strip_suffix('s') {
+        Some(stem) if stem.len() >= 3 && !keep => stem.to_string(),
+        _ => term.to_string(),
+    }
+}
@@ -0,0 +1,161 @@
+use super::*;
+
+/// The max number of power iterations to find a PCA component.
+const PCA_ITERATIONS: usize = 100;
+/// The change between iterations below which a component is found.
+const PCA_TOLERANCE: f32 = 1e-6;
+
+/// The transformation of the vectors before they're stored or used
+/// to search so the clients can send the raw model embeddings.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Transform {
+    /// Scales the vector to unit length for cosine similarity.
+    /// Vectors without magnitude are kept as is.
+    Normalize,
+    /// Subtracts the mean vector like the mean of the training data.
+    Center(Vector),
+    /// Multiplies the vector by the matrix where each row produces
+    /// a value of the output vector like a PCA projection. The output
+    /// dimension is the number of rows.
+    Rotate(Vec<Vector>),
+}
+
+impl Transform {
+    /// Creates a transform centering the vectors on their mean.
+    /// * `vectors`: Vectors to compute the mean from.
+    pub fn mean(vectors: &[Vector]) -> Result<Self, Error> {
+        let dimension = vectors.first().map_or(0, |v| v.len());
+        let mut mean = vec![0.0; dimension];
+        for vector in vectors {
+            if vector.len() != dimension {
+                let len = vector.len();
+                return Err(Error::invalid_dimension(len, dimension));
+            }
+
+            let pairs = mean.iter_mut().zip(vector.0.iter());
+            pairs.for_each(|(sum, x)| *sum += x);
+        }
+
+        let len = vectors.len().max(1) as f32;
+        mean.iter_mut().for_each(|sum| *sum /= len);
+        Ok(Transform::Center(mean.into()))
+    }
+
+    /// Creates the transforms projecting the vectors on their
+    /// principal components: centering on the mean followed by the
+    /// rotation to the components with the largest variance.
+    /// * `vectors`: Vectors to compute the components from.
+    /// * `dimension`: Number of components to keep.
+    pub fn pca(
+        vectors: &[Vector],
+        dimension: usize,
+    ) -> Result<Vec<Self>, Error> {
+        let max = vectors.first().map_or(0, |v| v.len());
+        if dimension == 0 || dimension > max {
+            return Err(Error::invalid_pca_dimension(dimension, max));
+        }
+
+        let center = Transform::mean(vectors)?;
+        let centered: Vec<Vector> = vectors
+            .par_iter()
+            .map(|vector| center.apply(vector))
+            .collect::<Result<_, _>>()?;
+
+        // Covariance matrix of the centered vectors.
+        let len = vectors.len() as f32;
+        let covariance: Vec<Vec<f32>> = (0..max)
+            .into_par_iter()
+            .map(|i| {
+                let mut row = vec![0.0; max];
+                for vector in centered.iter() {
+                    let x = vector.0[i];
+                    let pairs = row.iter_mut().zip(vector.0.iter());
+                    pairs.for_each(|(sum, y)| *sum += x * y);
+                }
+
+                row.iter_mut().for_each(|sum| *sum /= len);
+                row
+            })
+            .collect();
+
+        // Power iteration for each component orthogonal to the
+        // previous ones to find the eigenvectors by the eigenvalue.
+        let mut components: Vec<Vec<f32>> = Vec::with_capacity(dimension);
+        for i in 0..dimension {
+            let mut component = vec![0.0; max];
+            component[i] = 1.0;
+
+            for _ in 0..PCA_ITERATIONS {
+                let mut next: Vec<f32> =
+                    covariance.iter().map(|row| dot(row, &component)).collect();
+
+                for previous in components.iter() {
+                    let projection = dot(&next, previous);
+                    let pairs = next.iter_mut().zip(previous.iter());
+                    pairs.for_each(|(x, p)| *x -= projection * p);
+                }
+
+                let norm = dot(&next, &next).sqrt();
+                if norm <= f32::EPSILON {
+                    break;
+                }
+
+                next.iter_mut().for_each(|x| *x /= norm);
+                let change = 1.0 - dot(&next, &component).abs();
+                component = next;
+                if change < PCA_TOLERANCE {
+                    break;
+                }
+            }
+
+            components.push(component);
+        }
+
+        let rotation = components.into_iter().map(Vector).collect();
+        Ok(vec![center, Transform::Rotate(rotation)])
+    }
+
+    /// Returns the transformed vector.
+    /// * `vector`: Vector to transform.
+    pub fn apply(&self, vector: &Vector) -> Result<Vector, Error> {
+        let transformed = match self {
+            Transform::Normalize => {
+                let norm = vector.0.iter().map(|x| x * x).sum::<f32>().sqrt();
+                match norm > 0.0 {
+                    true => vector.0.iter().map(|x| x / norm).collect(),
+                    false => vector.0.clone(),
+                }
+            }
+            Transform::Center(mean) => {
+                validate_len(vector, mean.len())?;
+                let pairs = vector.0.iter().zip(mean.0.iter());
+                pairs.map(|(x, m)| x - m).collect()
+            }
+            Transform::Rotate(matrix) => {
+                let mut output = Vec::with_capacity(matrix.len());
+                for row in matrix {
+                    validate_len(vector, row.len())?;
+                    let pairs = vector.0.iter().zip(row.0.iter());
+                    output.push(pairs.map(|(x, w)| x * w).sum());
+                }
+
+                output
+            }
+        };
+
+        Ok(Vector(transformed))
+    }
+}
+
+/// Returns the dot product of the values.
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// Ensures the vector can be combined with the transform values.
+fn validate_len(vector: &Vector, expected: usize) -> Result<(), Error> {
+    match vector.len() == expected {
+        true => Ok(()),
+        false => Err(Error::invalid_dimension(vector.len(), expected)),
+    }
+}
@@ -5,11 +5,141 @@ pub const INVALID: VectorID = VectorID(u32::MAX);
 /// The M value for the HNSW algorithm.
 pub const M: usize = 32;
 
+/// Max number of vectors in a batch of the deterministic build.
+const MAX_BATCH: usize = 1024;
+
 pub trait Layer {
     type Slice: Deref<Target = [VectorID]>;
     fn nearest_iter(&self, vector_id: &VectorID) -> NearestIter<Self::Slice>;
 }
 
+/// Lowers the scheduling priority of the calling thread to the
+/// lowest nice value. Other platforms than Linux would lower the
+/// whole process so the priority is only lowered on Linux.
+pub fn lower_thread_priority() {
+    // On Linux, the process ID 0 refers to the calling thread.
+    #[cfg(target_os = "linux")]
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, 0, 19);
+    }
+}
+
+/// Returns the estimated heap size of a vector in bytes.
+pub fn vec_size<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * size_of::<T>()
+}
+
+/// Returns the estimated heap size of a hash map in bytes.
+/// Each bucket stores the key-value pair and a control byte.
+pub fn map_size<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
+
+/// Returns the estimated heap size of a record's vector and data.
+pub fn record_size(vector: &Vector, data: &Metadata) -> usize {
+    vec_size(&vector.0) + data.heap_size()
+}
+
+/// The access statistics of a vector record.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct Access {
+    pub last: u64,
+    pub count: u64,
+}
+
+/// Tracks record accesses for the eviction policy. This uses
+/// interior mutability so read operations can record accesses.
+#[derive(Debug, Default)]
+pub struct AccessTracker {
+    clock: AtomicU64,
+    records: RwLock<HashMap<VectorID, Access>>,
+}
+
+impl AccessTracker {
+    /// Records an access to the vector ID.
+    pub fn touch(&self, vector_id: &VectorID) {
+        let tick = self.clock.fetch_add(1, AtomicOrdering::Relaxed) + 1;
+        let mut records = self.records.write();
+        let access = records.entry(*vector_id).or_default();
+        access.last = tick;
+        access.count += 1;
+    }
+
+    /// Removes the access statistics of the vector ID.
+    pub fn remove(&self, vector_id: &VectorID) {
+        self.records.write().remove(vector_id);
+    }
+
+    /// Returns the estimated memory usage of the statistics in bytes.
+    pub fn memory_usage(&self) -> usize {
+        map_size(&self.records.read())
+    }
+
+    /// Returns the vector ID to evict based on the policy.
+    /// Ties are resolved by evicting the lowest vector ID first.
+    /// * `ids`: Vector IDs to choose from.
+    /// * `policy`: Eviction policy to use.
+    pub fn victim<'a>(
+        &self,
+        ids: impl Iterator<Item = &'a VectorID>,
+        policy: EvictionPolicy,
+    ) -> Option<VectorID> {
+        let records = self.records.read();
+        let key = |id: &&VectorID| {
+            let access = records.get(id).copied().unwrap_or_default();
+            match policy {
+                EvictionPolicy::Lru => (access.last, 0, **id),
+                EvictionPolicy::Lfu => (access.count, access.last, **id),
+            }
+        };
+
+        ids.min_by_key(key).copied()
+    }
+}
+
+impl AccessTracker {
+    /// Creates a tracker from the clock and the record statistics.
+    pub fn from_parts(clock: u64, records: HashMap<VectorID, Access>) -> Self {
+        Self { clock: AtomicU64::new(clock), records: RwLock::new(records) }
+    }
+
+    /// Returns the clock and the statistics sorted by vector ID.
+    pub fn parts(&self) -> (u64, Vec<(VectorID, Access)>) {
+        let clock = self.clock.load(AtomicOrdering::Relaxed);
+        let mut records: Vec<(VectorID, Access)> =
+            self.records.read().iter().map(|(id, a)| (*id, *a)).collect();
+        records.sort_by_key(|(id, _)| *id);
+        (clock, records)
+    }
+}
+
+impl Clone for AccessTracker {
+    fn clone(&self) -> Self {
+        let clock = self.clock.load(AtomicOrdering::Relaxed);
+        Self::from_parts(clock, self.records.read().clone())
+    }
+}
+
+impl Serialize for AccessTracker {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let clock = self.clock.load(AtomicOrdering::Relaxed);
+        (clock, &*self.records.read()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AccessTracker {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let (clock, records) =
+            <(u64, HashMap<VectorID, Access>)>::deserialize(deserializer)?;
+        Ok(Self::from_parts(clock, records))
+    }
+}
+
 pub struct NearestIter<T> {
     node: T,
     current: usize,
@@ -115,6 +245,12 @@ impl BaseNode {
     pub fn set(&mut self, index: usize, vector_id: &VectorID) {
         self.0[index] = *vector_id;
     }
+
+    /// Removes a vector ID from the base node and shifts
+    /// the remaining vector IDs to keep the order.
+    pub fn remove(&mut self, vector_id: &VectorID) {
+        remove_link(&mut self.0, vector_id);
+    }
 }
 
 impl Index<&VectorID> for [RwLock<BaseNode>] {
@@ -158,15 +294,55 @@ impl UpperNode {
         Self(nearest)
     }
 
-    pub fn set(&mut self, index: usize, vector_id: &VectorID) {
+    /// Inserts a vector ID to the upper node at the index.
+    pub fn insert(&mut self, index: usize, vector_id: &VectorID) {
+        if index >= self.0.len() {
+            return;
+        }
+
+        // Shift the vector IDs.
+        if self.0[index].is_valid() {
+            self.0.copy_within(index..M - 1, index + 1);
+        }
+
         self.0[index] = *vector_id;
     }
+
+    /// Removes a vector ID from the upper node and shifts
+    /// the remaining vector IDs to keep the order.
+    pub fn remove(&mut self, vector_id: &VectorID) {
+        remove_link(&mut self.0, vector_id);
+    }
 }
 
-impl<'a> Layer for &'a [UpperNode] {
+impl Default for UpperNode {
+    fn default() -> Self {
+        Self([INVALID; M])
+    }
+}
+
+/// Removes a vector ID from the node links and
+/// fills the freed position with an invalid ID.
+fn remove_link(links: &mut [VectorID], vector_id: &VectorID) {
+    let index = match links.iter().position(|id| id == vector_id) {
+        Some(index) => index,
+        None => return,
+    };
+
+    links.copy_within(index + 1.., index);
+    links[links.len() - 1] = INVALID;
+}
+
+/// The nodes of an upper layer by vector ID. Only a fraction of
+/// the vectors are on the upper layers so they're stored sparsely.
+pub type UpperLayer = HashMap<VectorID, UpperNode>;
+
+impl<'a> Layer for &'a UpperLayer {
     type Slice = &'a [VectorID];
     fn nearest_iter(&self, vector_id: &VectorID) -> NearestIter<Self::Slice> {
-        NearestIter::new(&self[vector_id.0 as usize].0)
+        // Vectors that aren't on the layer have no links.
+        let node = self.get(vector_id);
+        NearestIter::new(node.map_or(&[][..], |node| &node.0[..]))
     }
 }
 
@@ -216,8 +392,9 @@ impl Visited {
             return;
         }
 
+        let len = self.store.len();
         self.store.clear();
-        self.store.resize(self.store.len(), 0);
+        self.store.resize(len, 0);
         self.generation = 1;
     }
 }
@@ -234,6 +411,14 @@ pub struct Candidate {
 pub struct Search {
     pub ef: usize,
     pub visited: Visited,
+    /// Number of nodes whose neighbors were expanded.
+    pub hops: usize,
+    /// Number of distances computed to the query vector.
+    pub distances: usize,
+    /// Time after which the search stops expanding the candidates.
+    pub deadline: Option<Instant>,
+    /// True if the search stopped at the deadline.
+    pub stopped: bool,
     candidates: BinaryHeap<Reverse<Candidate>>,
     nearest: Vec<Candidate>,
     working: Vec<Candidate>,
@@ -262,6 +447,14 @@ impl Search {
                 }
             }
 
+            // Keep the nearest found so far once the time is up.
+            if self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                self.stopped = true;
+                break;
+            }
+
+            self.hops += 1;
             let layer_iter = layer.nearest_iter(&candidate.vector_id);
             for vector_id in layer_iter.take(links) {
                 self.push(&vector_id, vector, vectors);
@@ -282,8 +475,15 @@ impl Search {
             return;
         }
 
+        // Links to the slots of deleted records are skipped
+        // until the slots are reused by new records.
+        let other = match vectors.get(vector_id) {
+            Some(other) => other,
+            None => return,
+        };
+
         // Create a new candidate.
-        let other = &vectors[vector_id];
+        self.distances += 1;
         let distance = OrderedFloat::from(vector.distance(other));
         let new = Candidate { distance, vector_id: *vector_id };
 
@@ -318,6 +518,9 @@ impl Search {
         self.nearest.clear();
         self.working.clear();
         self.discarded.clear();
+        self.hops = 0;
+        self.distances = 0;
+        self.stopped = false;
     }
 
     /// Selects the nearest neighbors.
@@ -325,6 +528,76 @@ impl Search {
         &self.nearest
     }
 
+    /// Selects the neighbors using the heuristic from the HNSW paper.
+    /// * `layer`: Layer to find the neighbors of the candidates in.
+    /// * `vector`: Vector to select the neighbors for.
+    /// * `vectors`: Vectors of the collection.
+    /// * `links`: Max number of neighbors to select.
+    /// * `params`: Heuristic parameters.
+    pub fn select_heuristic<L: Layer>(
+        &mut self,
+        layer: L,
+        vector: &Vector,
+        vectors: &HashMap<VectorID, Vector>,
+        links: usize,
+        params: Heuristic,
+    ) -> &[Candidate] {
+        self.working.clear();
+        for &candidate in self.nearest.iter() {
+            self.working.push(candidate);
+            if !params.extend_candidates {
+                continue;
+            }
+
+            for vector_id in layer.nearest_iter(&candidate.vector_id) {
+                if !self.visited.insert(&vector_id) {
+                    continue;
+                }
+
+                let other = match vectors.get(&vector_id) {
+                    Some(other) => other,
+                    None => continue,
+                };
+
+                self.distances += 1;
+                let distance = OrderedFloat::from(vector.distance(other));
+                self.working.push(Candidate { distance, vector_id });
+            }
+        }
+
+        if params.extend_candidates {
+            self.working.sort();
+        }
+
+        self.nearest.clear();
+        self.discarded.clear();
+        for candidate in self.working.drain(..) {
+            if self.nearest.len() >= links {
+                break;
+            }
+
+            // Skip candidates closer to a selected neighbor than to
+            // the vector to create links bridging the clusters.
+            let point = &vectors[&candidate.vector_id];
+            let is_closer_to_neighbor = self.nearest.iter().any(|neighbor| {
+                let distance = point.distance(&vectors[&neighbor.vector_id]);
+                OrderedFloat::from(distance) < candidate.distance
+            });
+
+            match is_closer_to_neighbor {
+                true => self.discarded.push(candidate),
+                false => self.nearest.push(candidate),
+            }
+        }
+
+        if params.keep_pruned {
+            let free = links.saturating_sub(self.nearest.len());
+            self.nearest.extend(self.discarded.drain(..).take(free));
+        }
+
+        &self.nearest
+    }
+
     pub fn iter(&self) -> impl ExactSizeIterator<Item = Candidate> + '_ {
         self.nearest.iter().copied()
     }
@@ -339,6 +612,10 @@ impl Default for Search {
             working: Vec::new(),
             discarded: Vec::new(),
             ef: 5,
+            hops: 0,
+            distances: 0,
+            deadline: None,
+            stopped: false,
         }
     }
 }
@@ -368,8 +645,20 @@ impl SearchPool {
     }
 }
 
+/// The order of inserting the vectors when building the index.
+#[derive(Clone, Copy, Debug)]
+pub enum Insertion {
+    /// Inserts all vectors of a layer in parallel.
+    Parallel,
+    /// Inserts in ordered batches. See `insert_batches`.
+    Deterministic,
+    /// Inserts in parallel chunks of the size with a pause after each.
+    Throttled(usize, Duration),
+}
+
 pub struct IndexConstruction<'a> {
     pub search_pool: SearchPool,
+    pub entry_point: VectorID,
     pub top_layer: LayerID,
     pub base_layer: &'a [RwLock<BaseNode>],
     pub vectors: &'a HashMap<VectorID, Vector>,
@@ -385,22 +674,96 @@ impl<'a> IndexConstruction<'a> {
         &self,
         vector_id: &VectorID,
         layer: &LayerID,
-        layers: &[Vec<UpperNode>],
+        layers: &[UpperLayer],
+    ) {
+        let candidates = self.select(vector_id, layer, layers);
+        self.link(vector_id, &candidates);
+    }
+
+    /// Inserts the vector IDs into the layer in the insertion order.
+    /// * `range`: Vector IDs to insert.
+    /// * `layer`: Layer to insert into.
+    /// * `layers`: Upper layers.
+    /// * `insertion`: Order of the insertions.
+    pub fn insert_range(
+        &self,
+        range: Range<usize>,
+        layer: &LayerID,
+        layers: &[UpperLayer],
+        insertion: Insertion,
+    ) {
+        let insert = |i: usize| self.insert(&i.into(), layer, layers);
+        match insertion {
+            Insertion::Parallel => range.into_par_iter().for_each(insert),
+            Insertion::Deterministic => {
+                self.insert_batches(range, layer, layers)
+            }
+            Insertion::Throttled(size, pause) => {
+                let mut start = range.start;
+                while start < range.end {
+                    let end = min(start + size, range.end);
+                    (start..end).into_par_iter().for_each(insert);
+                    start = end;
+
+                    // Free the CPUs for other work between chunks.
+                    if start < range.end {
+                        sleep(pause);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Inserts the vector IDs in ordered batches. The neighbors of a
+    /// batch are selected in parallel against the index as it was
+    /// before the batch and then linked in order, which makes the
+    /// result independent of the thread scheduling. Batches grow with
+    /// the index so the first vectors are still inserted one by one.
+    /// * `range`: Vector IDs to insert.
+    /// * `layer`: Layer to insert into.
+    /// * `layers`: Upper layers.
+    pub fn insert_batches(
+        &self,
+        range: Range<usize>,
+        layer: &LayerID,
+        layers: &[UpperLayer],
     ) {
+        let mut start = range.start;
+        while start < range.end {
+            let size = (start / 4).clamp(1, MAX_BATCH);
+            let end = min(start + size, range.end);
+
+            let selected: Vec<Vec<Candidate>> = (start..end)
+                .into_par_iter()
+                .map(|i| self.select(&i.into(), layer, layers))
+                .collect();
+
+            for (i, candidates) in (start..end).zip(selected) {
+                self.link(&i.into(), &candidates);
+            }
+
+            start = end;
+        }
+    }
+
+    /// Finds the neighbors to link the vector ID to in the base layer
+    /// without modifying the index.
+    /// * `vector_id`: Vector ID to insert.
+    /// * `layer`: Layer to insert into.
+    /// * `layers`: Upper layers.
+    pub fn select(
+        &self,
+        vector_id: &VectorID,
+        layer: &LayerID,
+        layers: &[UpperLayer],
+    ) -> Vec<Candidate> {
         let vector = &self.vectors[vector_id];
 
         let (mut search, mut insertion) = self.search_pool.pop();
         insertion.ef = self.config.ef_construction;
 
-        // Find the first valid vector ID to push.
-        let validator = |i: usize| self.vectors.get(&i.into()).is_some();
-        let valid_id = (0..self.vectors.len())
-            .into_par_iter()
-            .find_first(|i| validator(*i))
-            .unwrap();
-
         search.reset();
-        search.push(&valid_id.into(), vector, self.vectors);
+        search.push(&self.entry_point, vector, self.vectors);
 
         for current_layer in self.top_layer.descend() {
             if current_layer <= *layer {
@@ -409,7 +772,7 @@ impl<'a> IndexConstruction<'a> {
 
             // Find the nearest neighbor candidates.
             if current_layer > *layer {
-                let layer = layers[current_layer.0 - 1].as_slice();
+                let layer = &layers[current_layer.0 - 1];
                 search.search(layer, vector, self.vectors, M);
                 search.cull();
             } else {
@@ -418,25 +781,44 @@ impl<'a> IndexConstruction<'a> {
             }
         }
 
-        // Select the neighbors.
-        let candidates = {
-            let candidates = search.select_simple();
-            &candidates[..Ord::min(candidates.len(), M)]
+        // Select the neighbors. The vector itself can be found
+        // when an existing vector is linked again.
+        let selected = match self.config.heuristic {
+            Some(params) => search.select_heuristic(
+                self.base_layer,
+                vector,
+                self.vectors,
+                M,
+                params,
+            ),
+            None => search.select_simple(),
         };
 
+        let candidates: Vec<Candidate> = selected
+            .iter()
+            .filter(|candidate| candidate.vector_id != *vector_id)
+            .take(M)
+            .copied()
+            .collect();
+
+        self.search_pool.push(&(search, insertion));
+        candidates
+    }
+
+    /// Links the vector ID with the selected neighbors both ways.
+    /// * `vector_id`: Vector ID to insert.
+    /// * `candidates`: Neighbors returned by `select`.
+    pub fn link(&self, vector_id: &VectorID, candidates: &[Candidate]) {
         for (i, candidate) in candidates.iter().enumerate() {
             let vid = candidate.vector_id;
             let old = &self.vectors[&vid];
             let distance = candidate.distance;
 
             // Function to sort the vectors by distance.
-            let ordering = |id: &VectorID| {
-                if !id.is_valid() {
-                    Ordering::Greater
-                } else {
-                    let other = &self.vectors[id];
-                    distance.cmp(&old.distance(other).into())
-                }
+            // Invalid and deleted links are ordered last.
+            let ordering = |id: &VectorID| match self.vectors.get(id) {
+                Some(other) => distance.cmp(&old.distance(other).into()),
+                None => Ordering::Greater,
             };
 
             // Find the correct index to insert at to keep the order.
@@ -446,9 +828,7 @@ impl<'a> IndexConstruction<'a> {
                 .unwrap_or_else(|error| error);
 
             self.base_layer[&vid].write().insert(index, vector_id);
-            self.base_layer[vector_id].write().set(i, vector_id);
+            self.base_layer[vector_id].write().set(i, &vid);
         }
-
-        self.search_pool.push(&(search, insertion));
     }
 }
@@ -85,6 +85,15 @@ impl Vector {
 }
 
 impl Vector {
+    /// Returns an error with the index of the first value that is
+    /// NaN or infinite. Such values break the distance ordering.
+    pub fn validate_finite(&self) -> Result<(), Error> {
+        match self.0.iter().position(|x| !x.is_finite()) {
+            Some(index) => Err(Error::non_finite_value(index, self.0[index])),
+            None => Ok(()),
+        }
+    }
+
     /// Returns the Euclidean distance between two vectors.
     pub fn distance(&self, other: &Self) -> f32 {
         assert_eq!(self.0.len(), other.0.len());
@@ -13,10 +13,19 @@ mod func;
 /// Convenience re-exports for the public APIs.
 pub mod prelude;
 
+pub use db::backend;
 pub use db::database;
+pub use func::aggregate;
 pub use func::collection;
 pub use func::err;
+pub use func::filter;
+pub use func::geo;
+pub use func::history;
 pub use func::metadata;
+pub use func::migration;
+pub use func::partition;
+pub use func::text;
+pub use func::transform;
 pub use func::vector;
 
 use pyo3::prelude::*;
@@ -49,6 +58,10 @@ fn sahomedb(py: Python, m: &PyModule) -> PyResult<()> {
 #[pymodule]
 fn collection_modules(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<collection::Config>()?;
+    m.add_class::<collection::EvictionPolicy>()?;
+    m.add_class::<collection::Heuristic>()?;
+    m.add_class::<collection::DedupPolicy>()?;
+    m.add_class::<collection::ScoreMode>()?;
     m.add_class::<collection::Record>()?;
     m.add_class::<collection::Collection>()?;
     m.add_class::<collection::SearchResult>()?;
@@ -71,6 +84,10 @@ fn database_modules(_py: Python, m: &PyModule) -> PyResult<()> {
 #[pymodule]
 fn prelude_modules(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<collection::Config>()?;
+    m.add_class::<collection::EvictionPolicy>()?;
+    m.add_class::<collection::Heuristic>()?;
+    m.add_class::<collection::DedupPolicy>()?;
+    m.add_class::<collection::ScoreMode>()?;
     m.add_class::<collection::Record>()?;
     m.add_class::<collection::Collection>()?;
     m.add_class::<collection::SearchResult>()?;
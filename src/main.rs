@@ -1 +1,47 @@
-fn main() {}
+use sahomedb::collection::Collection;
+use std::env::args;
+use std::fs::read;
+use std::process::exit;
+
+const USAGE: &str = "Usage: sahomedb inspect <collection-file>";
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let path = match args.as_slice() {
+        [_, command, path] if command == "inspect" => path,
+        _ => {
+            eprintln!("{USAGE}");
+            exit(2);
+        }
+    };
+
+    // Files are written by Collection::save_to.
+    let bytes = match read(path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("Failed to read {path}: {err}");
+            exit(1);
+        }
+    };
+
+    let summary = match Collection::inspect(&bytes) {
+        Ok(summary) => summary,
+        Err(err) => {
+            eprintln!("Invalid collection file: {}", err.message());
+            exit(1);
+        }
+    };
+
+    println!("Version: {}", summary.version);
+    println!("Size: {} bytes", summary.size);
+    println!("Dimension: {}", summary.dimension);
+    println!("Records: {}", summary.records);
+    println!("Slots: {} ({} free)", summary.slots, summary.free_slots);
+    println!("Entry point: {}", summary.entry_point);
+    println!("Read-only: {}", summary.read_only);
+    println!("Trash: {}", summary.trash);
+
+    for (layer, nodes) in summary.layers.iter().enumerate() {
+        println!("Layer {layer}: {nodes} nodes");
+    }
+}
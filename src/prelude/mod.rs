@@ -1,5 +1,14 @@
+pub use crate::backend::*;
 pub use crate::database::*;
+pub use crate::func::aggregate::*;
 pub use crate::func::collection::*;
 pub use crate::func::err::*;
+pub use crate::func::filter::*;
+pub use crate::func::geo::*;
+pub use crate::func::history::*;
 pub use crate::func::metadata::*;
+pub use crate::func::migration::*;
+pub use crate::func::partition::*;
+pub use crate::func::text::*;
+pub use crate::func::transform::*;
 pub use crate::func::vector::*;
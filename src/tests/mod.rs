@@ -1,11 +1,24 @@
+mod test_aggregate;
 mod test_collection;
 mod test_database;
+mod test_filter;
+mod test_partition;
 
+use crate::aggregate::{self, FacetValue};
+use crate::backend::*;
 use crate::collection::*;
 use crate::database::*;
+use crate::filter::{Filter, FilterStrategy};
+use crate::geo::*;
+use crate::metadata::*;
+use crate::migration::*;
+use crate::partition::*;
+use crate::transform::*;
 use crate::vector::*;
 use rayon::iter::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{create_dir_all, remove_dir_all};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const DIMENSION: usize = 128;
 const LEN: usize = 100;
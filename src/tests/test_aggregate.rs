@@ -0,0 +1,104 @@
+use super::*;
+
+/// Creates a collection of articles with tags and a year.
+fn create_article_collection() -> Collection {
+    let articles: [(&[&str], usize); 4] = [
+        (&["rust", "database"], 2021),
+        (&["rust", "search", "rust"], 2022),
+        (&["python"], 2023),
+        (&[], 2035),
+    ];
+
+    let mut records = Record::many_random(DIMENSION, articles.len());
+    for (record, (tags, year)) in records.iter_mut().zip(articles) {
+        let data = HashMap::from([
+            ("tags", Metadata::from(tags.to_vec())),
+            ("year", Metadata::from(year)),
+        ]);
+
+        record.data = Metadata::from(data);
+    }
+
+    let config = Config::default();
+    Collection::build(&config, &records).unwrap()
+}
+
+#[test]
+fn facet_counts() {
+    let collection = create_article_collection();
+    let facets = collection.facet_counts("tags");
+
+    let text = |value: &str| FacetValue::Text(value.into());
+    let counts: Vec<(FacetValue, usize)> =
+        facets.into_iter().map(|f| (f.value, f.count)).collect();
+
+    let expected = vec![
+        (text("rust"), 2),
+        (text("database"), 1),
+        (text("python"), 1),
+        (text("search"), 1),
+    ];
+
+    assert_eq!(counts, expected);
+}
+
+#[test]
+fn facet_counts_search_results() {
+    let collection = create_article_collection();
+    let vector = Vector::random(DIMENSION);
+    let results = collection.search(&vector, 10).unwrap();
+
+    let facets =
+        aggregate::facet_counts(results.iter().map(|r| &r.data), "year");
+    assert_eq!(facets.len(), 4);
+    assert!(facets.iter().all(|facet| facet.count == 1));
+}
+
+#[test]
+fn histogram() {
+    let collection = create_article_collection();
+    let buckets = collection.histogram("year", 10.0).unwrap();
+
+    let counts: Vec<(f32, usize)> =
+        buckets.iter().map(|b| (b.start, b.count)).collect();
+    assert_eq!(counts, vec![(2020.0, 3), (2030.0, 1)]);
+    assert_eq!(buckets[0].end, 2030.0);
+}
+
+#[test]
+fn histogram_invalid_interval() {
+    let collection = create_article_collection();
+    assert!(collection.histogram("year", 0.0).is_err());
+}
+
+#[test]
+fn vector_stats() {
+    let mut collection = Collection::new(&Config::default());
+    for vector in [[1.0, 0.0], [3.0, 0.0], [0.0, 2.0]] {
+        let record = Record::new(&vector.to_vec().into(), &Metadata::Null);
+        collection.insert(&record).unwrap();
+    }
+
+    let stats = collection.vector_stats(1.0).unwrap();
+    assert_eq!(stats.count, 3);
+    assert_eq!(stats.mean, vec![4.0 / 3.0, 2.0 / 3.0]);
+    assert!((stats.std[0] - 1.2472).abs() < 1e-3);
+
+    let norms: Vec<(f32, usize)> =
+        stats.norms.iter().map(|b| (b.start, b.count)).collect();
+    assert_eq!(norms, vec![(1.0, 1), (2.0, 1), (3.0, 1)]);
+    assert!(collection.vector_stats(0.0).is_err());
+
+    // The stats follow the deletes and updates without a rescan.
+    let record = Record::new(&vec![0.0, 4.0].into(), &Metadata::Null);
+    collection.delete(&VectorID(1)).unwrap();
+    collection.update(&VectorID(2), &record).unwrap();
+
+    let stats = collection.vector_stats(1.0).unwrap();
+    assert_eq!(stats.count, 2);
+    assert_eq!(stats.mean, vec![0.5, 2.0]);
+    assert_eq!(stats.norms.len(), 2);
+
+    let loaded = Collection::from_bytes(&collection.to_bytes()).unwrap();
+    assert_eq!(loaded.vector_stats(1.0).unwrap(), stats);
+}
@@ -9,21 +9,340 @@ fn build_large() {
     assert_eq!(collection.len(), len);
 }
 
+#[test]
+fn index_construction_links_neighbors() {
+    use crate::func::utils::*;
+    use parking_lot::RwLock;
+
+    let vectors: HashMap<VectorID, Vector> =
+        (0..3_usize).map(|i| (i.into(), Vector::random(DIMENSION))).collect();
+    let base_layer: Vec<RwLock<BaseNode>> =
+        (0..3).map(|_| RwLock::new(BaseNode::default())).collect();
+
+    let config = Config::default();
+    let state = IndexConstruction {
+        search_pool: SearchPool::new(vectors.len()),
+        entry_point: VectorID(0),
+        top_layer: LayerID(0),
+        base_layer: &base_layer,
+        vectors: &vectors,
+        config: &config,
+    };
+
+    // The inserted node used to be linked to itself instead of
+    // to its neighbors, leaving most of the records unreachable.
+    for i in 1..3_usize {
+        state.insert(&i.into(), &LayerID(0), &[]);
+    }
+
+    for (i, node) in base_layer.iter().enumerate() {
+        let node = node.read();
+        let links: Vec<VectorID> =
+            node.iter().take_while(|id| id.is_valid()).copied().collect();
+        assert_eq!(links.len(), 2);
+        assert!(!links.contains(&VectorID::from(i)));
+    }
+}
+
+#[test]
+fn build_with_threads() {
+    let records = Record::many_random(DIMENSION, LEN);
+    let config = Config::default();
+    let collection =
+        Collection::build_with_threads(&config, &records, 2).unwrap();
+    assert_eq!(collection.len(), LEN);
+}
+
+#[test]
+fn build_with_limits() {
+    let records = Record::many_random(DIMENSION, LEN);
+    let config = Config::default();
+    let limits = BuildLimits {
+        max_threads: 2,
+        chunk_size: 10,
+        pause: Duration::from_millis(1),
+        ..Default::default()
+    };
+
+    let collection =
+        Collection::build_with_limits(&config, &records, &limits).unwrap();
+    assert_eq!(collection.len(), LEN);
+
+    let limits = BuildLimits { max_memory: Some(1024), ..Default::default() };
+    assert!(Collection::build_with_limits(&config, &records, &limits).is_err());
+
+    let limits = BuildLimits { low_priority: true, ..Default::default() };
+    let collection =
+        Collection::build_with_limits(&config, &records, &limits).unwrap();
+    assert_eq!(collection.len(), LEN);
+}
+
+#[test]
+fn build_deterministic() {
+    // Enough records to build upper layers too.
+    let records = Record::many_random(DIMENSION, LEN * 10);
+    let config = Config::default();
+    let a = Collection::build_deterministic(&config, &records).unwrap();
+    let b = Collection::build_deterministic(&config, &records).unwrap();
+    assert_eq!(a.to_bytes(), b.to_bytes());
+
+    let results = a.search(&records[5].vector, 1).unwrap();
+    assert_eq!(results[0].id, 5);
+}
+
+#[test]
+fn build_from_iter() {
+    let records = (0..LEN).map(|_| Record::random(DIMENSION));
+    let config = Config::default();
+    let collection = Collection::build_from_iter(&config, records).unwrap();
+    assert_eq!(collection.len(), LEN);
+    assert_eq!(collection.dimension(), DIMENSION);
+
+    let vector = Vector::random(DIMENSION);
+    assert_eq!(collection.search(&vector, 10).unwrap().len(), 10);
+}
+
+#[test]
+fn build_from_stream() {
+    let mut records: VecDeque<Record> =
+        (0..LEN).map(|_| Record::random(DIMENSION)).collect();
+
+    let config = Config::default();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let build = Collection::build_from_stream(&config, || {
+        let record = records.pop_front();
+        async move { record }
+    });
+
+    let collection = runtime.block_on(build).unwrap();
+    assert_eq!(collection.len(), LEN);
+    assert_eq!(collection.dimension(), DIMENSION);
+}
+
+/// Creates records where the second is shorter and the fourth
+/// is longer than the test dimension.
+fn create_mismatched_records() -> Vec<Record> {
+    let mut records = Record::many_random(DIMENSION, 5);
+    records[1].vector = Vector::random(DIMENSION - 1);
+    records[3].vector = Vector::random(DIMENSION + 1);
+    records
+}
+
+#[test]
+fn build_with_policy_reject() {
+    let records = create_mismatched_records();
+    let config = Config::default();
+    let policy = DimensionPolicy::Reject;
+    let result =
+        Collection::build_with_policy(&config, &records, DIMENSION, policy);
+    assert!(result.is_err());
+}
+
+#[test]
+fn build_with_policy_skip() {
+    let records = create_mismatched_records();
+    let config = Config::default();
+    let policy = DimensionPolicy::Skip;
+    let (collection, report) =
+        Collection::build_with_policy(&config, &records, DIMENSION, policy)
+            .unwrap();
+
+    assert_eq!(collection.len(), 3);
+    assert_eq!(report.skipped, vec![1, 3]);
+
+    // The records after a skipped one are shifted to lower IDs.
+    assert_eq!(report.vector_id(1), None);
+    assert_eq!(report.vector_id(2), Some(VectorID(1)));
+    let id = report.vector_id(4).unwrap();
+    assert_eq!(collection.get(&id).unwrap().data, records[4].data);
+}
+
+#[test]
+fn build_with_policy_coerce() {
+    let records = create_mismatched_records();
+    let config = Config::default();
+    let policy = DimensionPolicy::Coerce;
+    let (collection, report) =
+        Collection::build_with_policy(&config, &records, DIMENSION, policy)
+            .unwrap();
+
+    assert_eq!(collection.len(), 5);
+    assert_eq!(report.padded, vec![1]);
+    assert_eq!(report.truncated, vec![3]);
+
+    let padded = collection.get(&VectorID(1)).unwrap().vector;
+    assert_eq!(padded.len(), DIMENSION);
+    assert_eq!(padded.0[DIMENSION - 1], 0.0);
+}
+
 #[test]
 fn insert() {
     let mut collection = create_collection();
 
     // Create a new record to insert.
     let new_record = Record::random(DIMENSION);
-    collection.insert(&new_record).unwrap();
+    let id = collection.insert(&new_record).unwrap();
 
     // Assert the new record is in the collection.
-    let id = VectorID::from(LEN);
+    assert_eq!(id, VectorID::from(LEN));
     assert_eq!(collection.len(), LEN + 1);
 
     assert_eq!(collection.get(&id).unwrap().data, new_record.data);
 }
 
+#[test]
+fn insert_reuse_deleted_slot() {
+    let mut collection = create_collection();
+
+    // Delete a record to free its slot.
+    let id = VectorID(10);
+    collection.delete(&id).unwrap();
+
+    // The new record should take over the freed slot.
+    let new_record = Record::random(DIMENSION);
+    let new_id = collection.insert(&new_record).unwrap();
+
+    assert_eq!(new_id, id);
+    assert_eq!(collection.len(), LEN);
+    assert_eq!(collection.get(&id).unwrap().data, new_record.data);
+}
+
+#[test]
+fn search_after_churn() {
+    let mut collection = create_collection();
+
+    // Replace half of the records with new ones.
+    for i in 0..LEN / 2 {
+        collection.delete(&VectorID::from(i)).unwrap();
+        collection.insert(&Record::random(DIMENSION)).unwrap();
+    }
+
+    // Deleted slots are reused so no new slot is allocated.
+    let ids = collection.list().unwrap().into_keys();
+    assert!(ids.into_iter().all(|id| id.0 < LEN as u32));
+
+    let query = Vector::random(DIMENSION);
+    let result = collection.search(&query, 5).unwrap();
+    assert_eq!(result.len(), 5);
+}
+
+#[test]
+fn search_after_delete_half() {
+    let mut collection = create_collection();
+    for i in (0..LEN).step_by(2) {
+        collection.delete(&VectorID::from(i)).unwrap();
+    }
+
+    // Links left to the deleted records are skipped by the search.
+    let query = Vector::random(DIMENSION);
+    let results = collection.search(&query, 10).unwrap();
+    assert_eq!(results.len(), 10);
+    assert!(results.iter().all(|r| r.id % 2 == 1));
+
+    let loaded = Collection::from_bytes(&collection.to_bytes()).unwrap();
+    assert_eq!(loaded.search(&query, 10).unwrap().len(), 10);
+}
+
+#[test]
+fn insert_creates_upper_layers() {
+    let len = 1000;
+    let mut collection = Collection::new(&Config::default());
+    let records = Record::many_random(DIMENSION, len);
+    for record in records.iter() {
+        collection.insert(record).unwrap();
+    }
+
+    // Inserted vectors are assigned random levels above the base layer.
+    let query = &records[0].vector;
+    let explain = collection.explain_search(query, 1, None).unwrap();
+    assert!(explain.layers.len() > 1);
+    assert_eq!(explain.results[0].id, 0);
+
+    // Each record should be found as its own nearest neighbor.
+    let found = (0..len).step_by(10).filter(|i| {
+        let result = collection.search(&records[*i].vector, 1).unwrap();
+        result[0].id == *i as u32
+    });
+
+    assert!(found.count() >= 95);
+
+    // Upper layers only hold the vectors inserted into them.
+    let summary = Collection::inspect(&collection.to_bytes()).unwrap();
+    assert_eq!(summary.layers[0], len);
+    assert!(summary.layers[1] < len / 2);
+}
+
+#[test]
+fn insert_duplicate_skip() {
+    let mut collection = create_collection();
+    collection.config.dedup = Some(DedupPolicy::Skip);
+
+    let existing = collection.get(&VectorID(5)).unwrap();
+    let record = Record::new(&existing.vector, &Metadata::from("new"));
+    let id = collection.insert(&record).unwrap();
+
+    assert_eq!(id, VectorID(5));
+    assert_eq!(collection.len(), LEN);
+    assert_eq!(collection.get(&id).unwrap().data, existing.data);
+}
+
+#[test]
+fn insert_duplicate_merge() {
+    let config =
+        Config { dedup: Some(DedupPolicy::Merge), ..Default::default() };
+    let mut collection = Collection::new(&config);
+
+    let vector = Vector::random(DIMENSION);
+    let data: HashMap<&str, usize> = HashMap::from([("a", 1), ("b", 2)]);
+    let id = collection.insert(&Record::new(&vector, &data.into())).unwrap();
+
+    let data: HashMap<&str, usize> = HashMap::from([("b", 3), ("c", 4)]);
+    let merged = collection.insert(&Record::new(&vector, &data.into()));
+    assert_eq!(merged.unwrap(), id);
+
+    let expected: HashMap<&str, usize> =
+        HashMap::from([("a", 1), ("b", 3), ("c", 4)]);
+    assert_eq!(collection.get(&id).unwrap().data, expected.into());
+    assert_eq!(collection.len(), 1);
+
+    // Vectors further than the dedup distance are inserted.
+    let record = Record::random(DIMENSION);
+    assert_ne!(collection.insert(&record).unwrap(), id);
+}
+
+#[test]
+fn read_only_rejects_writes() {
+    let mut collection = create_collection();
+    collection.set_read_only(true);
+
+    let record = Record::random(DIMENSION);
+    assert!(collection.insert(&record).is_err());
+    assert!(collection.update(&VectorID(0), &record).is_err());
+    assert!(collection.delete(&VectorID(0)).is_err());
+
+    // Reads are still served.
+    assert_eq!(collection.len(), LEN);
+    assert!(collection.get(&VectorID(0)).is_ok());
+
+    collection.set_read_only(false);
+    assert!(collection.insert(&record).is_ok());
+}
+
+#[test]
+fn insert_non_finite_value() {
+    let mut collection = create_collection();
+
+    let mut record = Record::random(DIMENSION);
+    record.vector.0[3] = f32::NAN;
+    assert!(collection.insert(&record).is_err());
+
+    // Searches reject the invalid vector too.
+    record.vector.0[3] = f32::INFINITY;
+    assert!(collection.search(&record.vector, 10).is_err());
+    assert!(collection.true_search(&record.vector, 10).is_err());
+    assert_eq!(collection.len(), LEN);
+}
+
 #[test]
 fn insert_invalid_dimension() {
     let mut collection = create_collection();
@@ -53,6 +372,62 @@ fn insert_data_type_object() {
     assert_eq!(collection.get(&id).unwrap().data, data.into());
 }
 
+#[test]
+fn insert_memory_limit() {
+    let mut collection = create_collection();
+
+    // Set the memory limit to the current memory usage.
+    let max = collection.memory_usage();
+    collection.config.max_memory = Some(max);
+
+    let record = Record::random(DIMENSION);
+    assert!(collection.insert(&record).is_err());
+    assert_eq!(collection.len(), LEN);
+}
+
+#[test]
+fn insert_record_limit() {
+    let mut collection = create_collection();
+    collection.config.max_records = Some(LEN);
+
+    let record = Record::random(DIMENSION);
+    assert!(collection.insert(&record).is_err());
+    assert_eq!(collection.len(), LEN);
+}
+
+#[test]
+fn insert_evict_lru() {
+    let mut collection = create_collection();
+    collection.config.max_records = Some(LEN);
+    collection.config.eviction = Some(EvictionPolicy::Lru);
+
+    // Access the oldest record so it's recently used.
+    collection.get(&VectorID(0)).unwrap();
+
+    let id = collection.insert(&Record::random(DIMENSION)).unwrap();
+
+    // The next oldest record is evicted and its slot reused.
+    assert_eq!(id, VectorID(1));
+    assert_eq!(collection.len(), LEN);
+    assert!(collection.contains(&VectorID(0)));
+}
+
+#[test]
+fn insert_evict_lfu() {
+    let mut collection = create_collection();
+    collection.config.max_records = Some(LEN);
+    collection.config.eviction = Some(EvictionPolicy::Lfu);
+
+    // Access all records except one at least once.
+    for i in 0..LEN - 1 {
+        collection.get(&VectorID::from(i)).unwrap();
+    }
+
+    let id = collection.insert(&Record::random(DIMENSION)).unwrap();
+    assert_eq!(id, VectorID::from(LEN - 1));
+    assert_eq!(collection.len(), LEN);
+}
+
 #[test]
 fn delete() {
     let mut collection = create_collection();
@@ -63,6 +438,165 @@ fn delete() {
     assert_eq!(collection.len(), LEN - 1);
 }
 
+#[test]
+fn delete_and_restore() {
+    let records = Record::many_random(DIMENSION, LEN);
+    let config = Config { trash_retention: Some(60), ..Default::default() };
+    let mut collection = Collection::build(&config, &records).unwrap();
+
+    let id = VectorID(3);
+    collection.delete(&id).unwrap();
+    assert!(!collection.contains(&id));
+    assert!(collection.trash().contains_key(&id));
+
+    let restored = collection.restore(&id).unwrap();
+    assert_eq!(collection.get(&restored).unwrap().data, records[3].data);
+    assert!(collection.trash().is_empty());
+    assert!(collection.restore(&id).is_err());
+}
+
+#[test]
+fn purge_trash_without_retention() {
+    let config = Config { trash_retention: Some(60), ..Default::default() };
+    let mut collection = Collection::build(&config, &[]).unwrap();
+    let id = collection.insert(&Record::random(DIMENSION)).unwrap();
+    collection.delete(&id).unwrap();
+
+    // Disabling the trash makes its records expired.
+    collection.config.trash_retention = None;
+    assert_eq!(collection.purge_trash(), 1);
+    assert!(collection.restore(&id).is_err());
+}
+
+#[test]
+fn search_with_entry_points() {
+    let collection = create_collection();
+    let vector = collection.get(&VectorID(7)).unwrap().vector;
+
+    let results = collection.search_with_entry_points(&vector, 10, 4);
+    let results = results.unwrap();
+    assert_eq!(results.len(), 10);
+    assert_eq!(results[0].id, 7);
+}
+
+#[test]
+fn search_over_max_n() {
+    let records = Record::many_random(DIMENSION, LEN);
+    let config = Config { max_n: Some(5), ..Default::default() };
+    let collection = Collection::build(&config, &records).unwrap();
+
+    let vector = Vector::random(DIMENSION);
+    assert_eq!(collection.search(&vector, 5).unwrap().len(), 5);
+    assert!(collection.search(&vector, 6).is_err());
+    assert!(collection.true_search(&vector, 6).is_err());
+}
+
+#[test]
+fn search_with_budget() {
+    let collection = create_collection();
+    let vector = Vector::random(DIMENSION);
+
+    let (results, partial) =
+        collection.search_with_budget(&vector, 10, 1000).unwrap();
+    assert_eq!(results.len(), 10);
+    assert!(!partial);
+
+    // A spent budget still returns the nearest found so far.
+    let (results, partial) =
+        collection.search_with_budget(&vector, 10, 0).unwrap();
+    assert!(partial);
+    assert!(results.len() <= 10);
+}
+
+#[test]
+fn search_adaptive() {
+    let collection = create_collection();
+    let vector = Vector::random(DIMENSION);
+
+    let results = collection.search_adaptive(&vector, 10, 200).unwrap();
+    let true_results = collection.true_search(&vector, 10).unwrap();
+    assert_eq!(results.len(), 10);
+    assert_eq!(results[0].id, true_results[0].id);
+}
+
+#[test]
+fn search_scored() {
+    let collection = create_collection();
+    let vector = collection.get(&VectorID(0)).unwrap().vector;
+
+    let results = collection.search_scored(&vector, 5, ScoreMode::Cosine);
+    let results = results.unwrap();
+    assert_eq!(results[0].id, 0);
+    assert!((results[0].score - 1.0).abs() < 1e-5);
+
+    let results = collection.search_scored(&vector, 5, ScoreMode::Similarity);
+    let scores: Vec<f32> = results.unwrap().iter().map(|r| r.score).collect();
+    assert!(scores.iter().all(|score| (0.0..=1.0).contains(score)));
+    assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+}
+
+#[test]
+fn upsert() {
+    let mut collection = create_collection();
+    let record = Record::random(DIMENSION);
+
+    // Existing records are updated.
+    let id = VectorID(5);
+    assert!(!collection.upsert(&id, &record).unwrap());
+    assert_eq!(collection.get(&id).unwrap().data, record.data);
+
+    // Missing records are created with the vector ID.
+    let id = VectorID::from(LEN + 3);
+    assert!(collection.upsert(&id, &record).unwrap());
+    assert_eq!(collection.len(), LEN + 1);
+    assert!(collection.contains(&id));
+
+    // The slots skipped by the vector ID are reused by inserts.
+    let next = collection.insert(&record).unwrap();
+    assert!(next.0 < id.0);
+    assert_eq!(collection.search(&record.vector, 1).unwrap().len(), 1);
+    assert!(Collection::from_bytes(&collection.to_bytes()).is_ok());
+
+    // Vector IDs far past the last slot are rejected.
+    let bytes = collection.to_bytes();
+    let id = VectorID::from(LEN + 100_000);
+    assert!(collection.upsert(&id, &record).is_err());
+    assert_eq!(collection.to_bytes(), bytes);
+}
+
+#[test]
+fn upsert_with_eviction_and_dedup() {
+    let config = Config {
+        max_records: Some(LEN),
+        eviction: Some(EvictionPolicy::Lru),
+        dedup: Some(DedupPolicy::Skip),
+        ..Default::default()
+    };
+
+    let mut collection = Collection::new(&config);
+    let records = Record::many_random(DIMENSION, LEN);
+    for record in records.iter() {
+        collection.insert(record).unwrap();
+    }
+
+    // The evicted record frees a slot but the vector ID is kept.
+    let id = VectorID::from(LEN + 3);
+    let record = Record::random(DIMENSION);
+    assert!(collection.upsert(&id, &record).unwrap());
+    assert_eq!(collection.len(), LEN);
+    assert_eq!(collection.get(&id).unwrap().data, record.data);
+
+    // A duplicate isn't created and leaves no skipped slots behind.
+    let id = VectorID::from(LEN + 10);
+    let summary = Collection::inspect(&collection.to_bytes()).unwrap();
+    assert!(collection.upsert(&id, &records[50]).is_err());
+    assert!(!collection.contains(&id));
+
+    let after = Collection::inspect(&collection.to_bytes()).unwrap();
+    assert_eq!(after.slots, summary.slots);
+    assert_eq!(after.free_slots, summary.free_slots);
+}
+
 #[test]
 fn update() {
     let mut collection = create_collection();
@@ -100,6 +634,533 @@ fn search() {
     assert_eq!(distances.contains(&result[0].distance), true);
 }
 
+#[test]
+fn explain_search() {
+    let collection = create_collection();
+    let query = Vector::random(DIMENSION);
+    let explain = collection.explain_search(&query, 5, None).unwrap();
+
+    assert_eq!(explain.results.len(), 5);
+    assert_eq!(explain.filtered, 0);
+
+    // The traversal ends in the base layer after visiting some nodes.
+    let base = explain.layers.last().unwrap();
+    assert_eq!(base.layer, 0);
+    assert!(base.hops > 0);
+
+    let distances = explain.layers.iter().map(|l| l.distance_computations);
+    assert!(distances.sum::<usize>() <= LEN);
+
+    // The results must match the regular search.
+    let result = collection.search(&query, 5).unwrap();
+    let ids: Vec<u32> = result.iter().map(|r| r.id).collect();
+    let explained: Vec<u32> = explain.results.iter().map(|r| r.id).collect();
+    assert_eq!(ids, explained);
+}
+
+#[test]
+fn repair_after_delete() {
+    // Create two clusters far from each other. Only the first
+    // records of the second cluster link to the first cluster.
+    let mut records = Record::many_random(DIMENSION, 80);
+    for record in records[40..].iter_mut() {
+        let vector = record.vector.0.iter().map(|x| x + 100.0).collect();
+        record.vector = Vector(vector);
+    }
+
+    let config = Config::default();
+    let mut collection = Collection::build(&config, &records).unwrap();
+    assert!(collection.unreachable_records().is_empty());
+
+    // Deleting the linking records cuts off the rest of the cluster.
+    for i in 40..72_usize {
+        collection.delete(&VectorID::from(i)).unwrap();
+    }
+
+    let unreachable = collection.unreachable_records();
+    assert!(!unreachable.is_empty());
+
+    assert_eq!(collection.repair(), unreachable.len());
+    assert!(collection.unreachable_records().is_empty());
+
+    // The repaired records can be found again.
+    let query = &records[75].vector;
+    let result = collection.search(query, 1).unwrap();
+    assert_eq!(result[0].id, 75);
+}
+
+#[test]
+fn repair_every_delete() {
+    let config = Config { repair_every: Some(1), ..Default::default() };
+    let records = Record::many_random(DIMENSION, LEN);
+    let mut collection = Collection::build(&config, &records).unwrap();
+
+    for i in 0..LEN / 2 {
+        collection.delete(&VectorID::from(i)).unwrap();
+        assert!(collection.unreachable_records().is_empty());
+    }
+}
+
+#[test]
+fn search_with_heuristic() {
+    let heuristic = Heuristic { extend_candidates: true, keep_pruned: true };
+    let config = Config { heuristic: Some(heuristic), ..Default::default() };
+
+    // Create records clustered around a few centers.
+    let centers: Vec<Vector> =
+        (0..10).map(|_| Vector::random(DIMENSION)).collect();
+
+    let mut records = Record::many_random(DIMENSION, 1000);
+    for (i, record) in records.iter_mut().enumerate() {
+        let center = centers[i % centers.len()].0.iter();
+        let noise = record.vector.0.iter();
+        let vector = center.zip(noise).map(|(c, n)| c * 10.0 + n * 0.1);
+        record.vector = Vector(vector.collect());
+    }
+
+    let collection = Collection::build(&config, &records).unwrap();
+
+    // Each record should be found as its own nearest neighbor.
+    let found = (0..records.len()).step_by(10).filter(|i| {
+        let result = collection.search(&records[*i].vector, 1).unwrap();
+        result[0].id == *i as u32
+    });
+
+    assert!(found.count() >= 95);
+}
+
+#[test]
+fn search_after_delete_entry_point() {
+    let mut collection = create_collection();
+
+    // The first record is the entry point of a built collection.
+    let id = VectorID(0);
+    collection.delete(&id).unwrap();
+
+    let query = Vector::random(DIMENSION);
+    let result = collection.search(&query, 5).unwrap();
+
+    assert_eq!(result.len(), 5);
+    assert!(result.iter().all(|res| res.id != id.0));
+}
+
+#[test]
+fn search_after_delete_all() {
+    let mut collection = create_collection();
+    for i in 0..LEN {
+        collection.delete(&VectorID::from(i)).unwrap();
+    }
+
+    // A new record becomes the entry point of the empty collection.
+    let record = Record::random(DIMENSION);
+    let id = collection.insert(&record).unwrap();
+
+    let result = collection.search(&record.vector, 1).unwrap();
+    assert_eq!(result[0].id, id.0);
+}
+
+fn create_text_collection() -> Collection {
+    let texts = [
+        "The quick brown fox jumps over the lazy dog.",
+        "Searching vectors with an embedded database.",
+        "A lazy afternoon in the park.",
+    ];
+
+    let records: Vec<Record> = texts
+        .iter()
+        .map(|text| {
+            let data = HashMap::from([("text", *text), ("kind", "note")]);
+            Record::new(&Vector::random(DIMENSION), &data.into())
+        })
+        .collect();
+
+    let config = Config::default();
+    let mut collection = Collection::build(&config, &records).unwrap();
+    collection.create_text_index(vec!["text".into()]);
+    collection
+}
+
+#[test]
+fn text_search() {
+    let collection = create_text_collection();
+
+    // The query terms are stemmed to match the indexed text.
+    let result = collection.text_search("search databases", 5).unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].id, 1);
+
+    // Only the indexed fields are searchable.
+    let result = collection.text_search("note", 5).unwrap();
+    assert!(result.is_empty());
+}
+
+#[test]
+fn text_search_without_index() {
+    let collection = create_collection();
+    assert!(collection.text_search("query", 5).is_err());
+}
+
+#[test]
+fn text_search_after_delete() {
+    let mut collection = create_text_collection();
+    collection.delete(&VectorID(0)).unwrap();
+
+    let result = collection.text_search("lazy", 5).unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].id, 2);
+}
+
+#[test]
+fn hybrid_search() {
+    let collection = create_text_collection();
+    let record = collection.get(&VectorID(2)).unwrap();
+
+    // The record matches both the vector and the text query.
+    let result = collection.hybrid_search(&record.vector, "lazy", 3).unwrap();
+    assert_eq!(result.len(), 3);
+    assert_eq!(result[0].id, 2);
+}
+
+#[test]
+fn transform_normalize() {
+    let mut collection = Collection::new(&Config::default());
+    collection.set_transforms(vec![Transform::Normalize]).unwrap();
+
+    let records = Record::many_random(DIMENSION, LEN);
+    for record in records.iter() {
+        let id = collection.insert(record).unwrap();
+        let norm: f32 =
+            collection.get(&id).unwrap().vector.0.iter().map(|x| x * x).sum();
+        assert!((norm - 1.0).abs() < 1e-4);
+    }
+
+    // Raw queries are normalized before searching.
+    let scaled: Vec<f32> =
+        records[0].vector.0.iter().map(|x| x * 10.0).collect();
+    let results = collection.search(&scaled.into(), 5).unwrap();
+    assert_eq!(results[0].id, 0);
+
+    // The transforms are kept in the binary format.
+    let bytes = collection.to_bytes();
+    let loaded = Collection::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded.transforms(), collection.transforms());
+}
+
+#[test]
+fn transform_center_invalid_dimension() {
+    let mut collection = Collection::new(&Config::default());
+    let center = Transform::Center(vec![0.5; DIMENSION].into());
+    collection.set_transforms(vec![center]).unwrap();
+
+    let record = Record::random(DIMENSION + 1);
+    assert!(collection.insert(&record).is_err());
+    assert!(collection.is_empty());
+}
+
+#[test]
+fn dedup_with_center_transform() {
+    let config = Config {
+        dedup: Some(DedupPolicy::Skip),
+        dedup_distance: 0.01,
+        ..Default::default()
+    };
+
+    let mut collection = Collection::new(&config);
+    let center = Transform::Center(vec![5.0; DIMENSION].into());
+    collection.set_transforms(vec![center]).unwrap();
+
+    let records = Record::many_random(DIMENSION, LEN);
+    for record in records.iter() {
+        collection.insert(record).unwrap();
+    }
+
+    // The near-copy is compared in the transformed space once.
+    let mut vector = records[10].vector.clone();
+    vector.0[0] += 0.001;
+    let record = Record::new(&vector, &records[10].data);
+    assert_eq!(collection.insert(&record).unwrap(), VectorID(10));
+    assert_eq!(collection.len(), LEN);
+}
+
+#[test]
+fn reduce_dimension() {
+    let collection = create_collection();
+    let reduced = collection.reduce_dimension(16).unwrap();
+
+    assert_eq!(reduced.len(), LEN);
+    assert_eq!(reduced.dimension(), 16);
+    assert_eq!(reduced.transforms().len(), 2);
+
+    // Queries in the original dimension are projected.
+    let vector = collection.get(&VectorID(0)).unwrap().vector;
+    let results = reduced.search(&vector, 5).unwrap();
+    assert_eq!(results[0].id, 0);
+
+    assert!(collection.reduce_dimension(DIMENSION + 1).is_err());
+}
+
+#[test]
+fn reduce_dimension_keeps_ids_and_state() {
+    let config = Config { trash_retention: Some(60), ..Default::default() };
+    let records = Record::many_random(DIMENSION, LEN);
+    let mut collection = Collection::build(&config, &records).unwrap();
+    collection.set_model(Some("model".into()));
+    collection.set_versioned(true);
+    collection.update(&VectorID(3), &Record::random(DIMENSION)).unwrap();
+    collection.delete(&VectorID(5)).unwrap();
+    collection.set_read_only(true);
+
+    let mut reduced = collection.reduce_dimension(16).unwrap();
+    assert_eq!(reduced.len(), LEN - 1);
+    assert_eq!(reduced.model(), Some("model".into()));
+    assert!(reduced.is_versioned());
+    assert!(reduced.is_read_only());
+    assert!(Collection::from_bytes(&reduced.to_bytes()).is_ok());
+
+    // The records keep their vector IDs.
+    assert!(!reduced.contains(&VectorID(5)));
+    let id = VectorID::from(LEN - 1);
+    assert_eq!(reduced.get(&id).unwrap().data, records[LEN - 1].data);
+    let results = reduced.search(&records[LEN - 1].vector, 1).unwrap();
+    assert_eq!(results[0].id, id.0);
+
+    // The trashed record is restored in the new dimension.
+    reduced.set_read_only(false);
+    let restored = reduced.restore(&VectorID(5)).unwrap();
+    assert_eq!(reduced.get(&restored).unwrap().vector.len(), 16);
+}
+
+#[test]
+fn build_with_checkpoint() {
+    let path = "data/015.ckpt";
+    create_dir_all("data").unwrap();
+
+    let config = Config::default();
+    let records = Record::many_random(DIMENSION, LEN);
+    let collection =
+        Collection::build_with_checkpoint(&config, &records, path, 10).unwrap();
+
+    // The checkpoint is removed once the build completes.
+    assert_eq!(collection.len(), LEN);
+    assert!(!std::path::Path::new(path).exists());
+
+    // An unreadable checkpoint isn't resumed from.
+    std::fs::write(path, b"invalid").unwrap();
+    let result = Collection::build_with_checkpoint(&config, &records, path, 10);
+    assert!(result.is_err());
+
+    // A checkpoint of other records isn't resumed from.
+    let header = (DIMENSION, LEN, 1_usize, 0_u64);
+    let header = bincode::serialize(&header).unwrap();
+    let len = (header.len() as u64).to_le_bytes();
+    std::fs::write(path, [&len[..], &header].concat()).unwrap();
+
+    let result = Collection::build_with_checkpoint(&config, &records, path, 10);
+    assert!(result.unwrap_err().message().contains("doesn't match"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn validate_records() {
+    let config = Config { max_records: Some(LEN + 2), ..Default::default() };
+    let records = Record::many_random(DIMENSION, LEN);
+    let collection = Collection::build(&config, &records).unwrap();
+
+    let mut records = Record::many_random(DIMENSION, 4);
+    records[1] = Record::random(DIMENSION + 1);
+
+    // Only the records after the limit is reached are rejected.
+    let errors = collection.validate_records(&records).unwrap();
+    let indices: Vec<usize> = errors.iter().map(|(i, _)| *i).collect();
+    assert_eq!(indices, vec![1, 3]);
+    assert!(errors[0].1.contains("Invalid vector dimension"));
+
+    // Nothing is inserted by the validation.
+    assert_eq!(collection.len(), LEN);
+}
+
+#[test]
+fn validate_records_memory_and_dedup() {
+    let mut collection = create_collection();
+    let max = collection.memory_usage() + 1;
+    collection.config.max_memory = Some(max);
+
+    // The first record fills the memory left.
+    let records = Record::many_random(DIMENSION, 3);
+    let errors = collection.validate_records(&records).unwrap();
+    let indices: Vec<usize> = errors.iter().map(|(i, _)| *i).collect();
+    assert_eq!(indices, vec![1, 2]);
+    assert!(errors[0].1.contains("memory limit"));
+
+    // Evicting records makes room for all of them.
+    collection.config.eviction = Some(EvictionPolicy::Lru);
+    assert!(collection.validate_records(&records).unwrap().is_empty());
+
+    // Duplicates of the collection or the records aren't created.
+    collection.config.eviction = None;
+    collection.config.max_memory = None;
+    collection.config.max_records = Some(LEN + 1);
+    collection.config.dedup = Some(DedupPolicy::Skip);
+
+    let existing = collection.get(&VectorID(0)).unwrap();
+    let new = Record::random(DIMENSION);
+    let records = [existing, new.clone(), new, Record::random(DIMENSION)];
+    let errors = collection.validate_records(&records).unwrap();
+    let indices: Vec<usize> = errors.iter().map(|(i, _)| *i).collect();
+    assert_eq!(indices, vec![3]);
+}
+
+#[test]
+fn versioned_reads_as_of() {
+    let mut collection = create_collection();
+    assert!(collection.get_as_of(&VectorID(0), 0).is_err());
+    collection.set_versioned(true);
+
+    let id = VectorID(0);
+    let old = collection.get(&id).unwrap();
+    let new = Record::random(DIMENSION);
+
+    // Records from before the versioning exist since the epoch.
+    let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    std::thread::sleep(Duration::from_secs(1));
+    collection.update(&id, &new).unwrap();
+    collection.delete(&VectorID(1)).unwrap();
+
+    let past = collection.get_as_of(&id, before.as_secs()).unwrap();
+    assert_eq!(past.unwrap().vector, old.vector);
+    let now = u64::MAX;
+    assert_eq!(
+        collection.get_as_of(&id, now).unwrap().unwrap().vector,
+        new.vector
+    );
+
+    // The deleted record is still found as of the past time.
+    let results =
+        collection.search_as_of(&old.vector, 1, before.as_secs()).unwrap();
+    assert_eq!(results[0].id, 0);
+    assert_eq!(results[0].distance, 0.0);
+    assert!(collection.get_as_of(&VectorID(1), now).unwrap().is_none());
+    assert!(collection
+        .get_as_of(&VectorID(1), before.as_secs())
+        .unwrap()
+        .is_some());
+
+    // The history is kept in the binary format.
+    let loaded = Collection::from_bytes(&collection.to_bytes()).unwrap();
+    let past = loaded.get_as_of(&id, before.as_secs()).unwrap();
+    assert_eq!(past.unwrap().vector, old.vector);
+}
+
+#[test]
+fn versioned_reads_retention() {
+    let mut collection = create_collection();
+    collection.set_versioned(true);
+
+    let id = VectorID(0);
+    let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    std::thread::sleep(Duration::from_secs(1));
+    collection.update(&id, &Record::random(DIMENSION)).unwrap();
+    assert!(collection.get_as_of(&id, before.as_secs()).unwrap().is_some());
+
+    // Reads before the retention fail instead of missing versions.
+    collection.config.history_retention = Some(60);
+    assert!(collection.get_as_of(&id, 0).is_err());
+    assert!(collection.get_as_of(&id, u64::MAX).unwrap().is_some());
+
+    // The retention is kept in the binary format.
+    let loaded = Collection::from_bytes(&collection.to_bytes()).unwrap();
+    assert_eq!(loaded.config.history_retention, Some(60));
+
+    // Versions past the retention are dropped on the next update.
+    std::thread::sleep(Duration::from_secs(1));
+    collection.config.history_retention = Some(0);
+    collection.update(&id, &Record::random(DIMENSION)).unwrap();
+    collection.config.history_retention = None;
+    assert!(collection.get_as_of(&id, before.as_secs()).unwrap().is_none());
+}
+
+#[test]
+fn migrate_data() {
+    let mut collection = Collection::new(&Config::default());
+    let values = ["1", "2", "x"];
+    for value in values {
+        let data = HashMap::from([("count", value)]);
+        let record = Record::new(&Vector::random(DIMENSION), &data.into());
+        collection.insert(&record).unwrap();
+    }
+
+    // The invalid text fails the cast and nothing is changed.
+    let cast = Migration::Cast("count".into(), MetadataKind::Integer);
+    assert!(collection.migrate_data(&[cast]).is_err());
+
+    collection.delete(&VectorID(2)).unwrap();
+    let migrations = [
+        Migration::Rename("count".into(), "total".into()),
+        Migration::Cast("total".into(), MetadataKind::Integer),
+        Migration::SetDefault("tags".into(), Metadata::Array(vec![])),
+    ];
+
+    assert_eq!(collection.migrate_data(&migrations).unwrap(), 2);
+    let data = collection.get(&VectorID(1)).unwrap().data;
+    assert_eq!(data.field("total"), Some(&Metadata::Integer(2)));
+    assert_eq!(data.field("count"), None);
+    assert_eq!(data.field("tags"), Some(&Metadata::Array(vec![])));
+
+    // Casting to the same type and renaming to itself change nothing.
+    let migrations = [
+        Migration::Cast("total".into(), MetadataKind::Integer),
+        Migration::Rename("total".into(), "total".into()),
+    ];
+
+    assert_eq!(collection.migrate_data(&migrations).unwrap(), 0);
+
+    // Renaming onto an existing field fails instead of overwriting it.
+    let rename = Migration::Rename("total".into(), "tags".into());
+    assert!(collection.migrate_data(&[rename]).is_err());
+    let data = collection.get(&VectorID(1)).unwrap().data;
+    assert_eq!(data.field("tags"), Some(&Metadata::Array(vec![])));
+}
+
+#[test]
+fn build_invalid_config() {
+    let records = Record::many_random(DIMENSION, LEN);
+    let configs = [
+        Config { ml: 1.0, ..Default::default() },
+        Config { ef_search: 0, ..Default::default() },
+        Config { dedup_distance: -1.0, ..Default::default() },
+    ];
+
+    for config in configs {
+        assert!(config.validate().is_err());
+        assert!(Collection::build(&config, &records).is_err());
+        assert!(Collection::try_new(&config).is_err());
+    }
+
+    assert!(Config::default().validate().is_ok());
+
+    // Collections with an invalid config aren't decoded.
+    let mut collection = create_collection();
+    collection.config.ml = 1.0;
+    assert!(Collection::from_bytes(&collection.to_bytes()).is_err());
+}
+
+#[test]
+fn model_mismatch() {
+    let mut collection = create_collection();
+    collection.set_model(Some("model-a".into()));
+
+    let record = Record::random(DIMENSION);
+    assert!(collection.insert_with_model(&record, "model-b").is_err());
+    assert!(collection.insert_with_model(&record, "model-a").is_ok());
+
+    let vector = Vector::random(DIMENSION);
+    assert!(collection.search_with_model(&vector, 5, "model-b").is_err());
+
+    // The model tag is kept in the binary format.
+    let loaded = Collection::from_bytes(&collection.to_bytes()).unwrap();
+    assert_eq!(loaded.model(), Some("model-a".into()));
+}
+
 #[test]
 fn get() {
     let records = Record::many_random(DIMENSION, LEN);
@@ -115,6 +1176,43 @@ fn get() {
     assert_eq!(record.vector, records[index].vector);
 }
 
+#[test]
+fn memory_usage() {
+    let mut collection = create_collection();
+    let usage = collection.memory_usage();
+
+    // The raw vectors alone take 4 bytes per float.
+    assert!(usage > LEN * DIMENSION * 4);
+
+    collection.insert(&Record::random(DIMENSION)).unwrap();
+    assert!(collection.memory_usage() > usage);
+}
+
+#[test]
+fn memory_usage_trash_and_history() {
+    let config = Config { trash_retention: Some(60), ..Default::default() };
+    let records = Record::many_random(DIMENSION, LEN);
+    let mut collection = Collection::build(&config, &records).unwrap();
+    let usage = collection.memory_usage();
+
+    // Deleted records are still kept in the trash.
+    collection.delete(&VectorID(0)).unwrap();
+    assert!(collection.memory_usage() >= usage);
+
+    // Purging the trash frees the record.
+    let usage = collection.memory_usage();
+    collection.config.trash_retention = None;
+    collection.purge_trash();
+    assert!(collection.memory_usage() < usage);
+
+    // Past versions are kept in the history.
+    collection.set_versioned(true);
+    let usage = collection.memory_usage();
+    let record = Record::random(DIMENSION);
+    collection.update(&VectorID(1), &record).unwrap();
+    assert!(collection.memory_usage() >= usage + DIMENSION * 4);
+}
+
 #[test]
 fn list() {
     let collection = create_collection();
@@ -122,3 +1220,150 @@ fn list() {
     assert_eq!(list.len(), LEN);
     assert_eq!(list.len(), collection.len());
 }
+
+#[test]
+fn save_to_and_load_from() {
+    let mut collection = create_collection();
+    collection.delete(&VectorID(1)).unwrap();
+    collection.create_text_index(vec!["text".into()]);
+
+    let path = "data/012.bin";
+    create_dir_all("data").unwrap();
+    collection.save_to(path).unwrap();
+    let loaded = Collection::load_from(path).unwrap();
+
+    assert_eq!(loaded.len(), LEN - 1);
+    assert_eq!(loaded.list().unwrap().len(), LEN - 1);
+    assert_eq!(loaded.to_bytes(), collection.to_bytes());
+
+    // The loaded collection returns the same search results.
+    let query = Vector::random(DIMENSION);
+    let ids = |c: &Collection| -> Vec<u32> {
+        c.search(&query, 5).unwrap().iter().map(|r| r.id).collect()
+    };
+
+    assert_eq!(ids(&loaded), ids(&collection));
+
+    // The binary format is smaller than the generic serialization.
+    let generic = bincode::serialize(&collection).unwrap();
+    assert!(collection.to_bytes().len() < generic.len());
+}
+
+#[test]
+fn inspect_bytes() {
+    let collection = create_collection();
+    let bytes = collection.to_bytes();
+
+    let summary = Collection::inspect(&bytes).unwrap();
+    assert_eq!(summary.version, bytes[4]);
+    assert_eq!(summary.size, bytes.len());
+    assert_eq!(summary.dimension, DIMENSION);
+    assert_eq!(summary.records, LEN);
+    assert_eq!(summary.layers[0], LEN);
+
+    // Truncated data is reported instead of decoded partially.
+    assert!(Collection::inspect(&bytes[..bytes.len() / 2]).is_err());
+}
+
+#[test]
+fn from_bytes_invalid() {
+    let bytes = create_collection().to_bytes();
+    assert!(Collection::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Collection::from_bytes(b"invalid").is_err());
+
+    // Reject files written by a newer version of the format.
+    let mut newer = bytes.clone();
+    newer[4] += 1;
+    assert!(Collection::from_bytes(&newer).is_err());
+}
+
+#[test]
+fn from_bytes_invalid_structure() {
+    let mut collection = create_collection();
+    collection.config.trash_retention = Some(60);
+    collection.set_versioned(true);
+    collection.delete(&VectorID(3)).unwrap();
+    collection.update(&VectorID(4), &Record::random(DIMENSION)).unwrap();
+    collection.purge_trash();
+
+    let value = serde_json::to_value(&collection).unwrap();
+    let decode = |value: serde_json::Value| {
+        let collection: Collection = serde_json::from_value(value).unwrap();
+        Collection::from_bytes(&collection.to_bytes())
+    };
+
+    assert!(decode(value.clone()).is_ok());
+
+    // Free slots must be unique and within the slots.
+    for slot in [3, LEN as u32] {
+        let mut invalid = value.clone();
+        let free_slots = invalid["free_slots"].as_array_mut().unwrap();
+        free_slots.push(slot.into());
+        assert!(decode(invalid).is_err());
+    }
+
+    // Kept records must have the collection dimension.
+    for path in ["/trash/0/record/vector", "/history/versions/0/record/vector"]
+    {
+        let mut invalid = value.clone();
+        let vector = invalid.pointer_mut(path).unwrap();
+        vector.as_array_mut().unwrap().pop();
+        assert!(decode(invalid).is_err());
+    }
+}
+
+#[test]
+fn from_bytes_sections() {
+    let mut collection = create_collection();
+    collection.config.trash_retention = Some(60);
+    collection.delete(&VectorID(3)).unwrap();
+    let bytes = collection.to_bytes();
+    assert_eq!(Collection::from_bytes(&bytes).unwrap().trash().len(), 1);
+
+    // Sections the version doesn't know are rejected.
+    let mut unknown = bytes.clone();
+    unknown.extend_from_slice(&[99, 0]);
+    let err = Collection::from_bytes(&unknown).unwrap_err();
+    assert!(err.message().contains("Unknown section: 99."));
+
+    // The trash section can't be repeated.
+    let mut repeated = bytes.clone();
+    repeated.extend_from_slice(&[4, 1, 0]);
+    assert!(Collection::from_bytes(&repeated).is_err());
+}
+
+#[test]
+fn from_bytes_nested_metadata() {
+    let mut data = Metadata::from("leaf");
+    for _ in 0..200 {
+        data = Metadata::Array(vec![data]);
+    }
+
+    let mut collection = Collection::new(&Config::default());
+    let vector = Vector::random(DIMENSION);
+    collection.insert(&Record::new(&vector, &data)).unwrap();
+
+    let err = Collection::from_bytes(&collection.to_bytes()).unwrap_err();
+    assert!(err.message().contains("nested too deep"));
+}
+
+#[test]
+fn delete_keeps_entry_point_on_upper_layers() {
+    let mut collection = Collection::new(&Config::default());
+    let records = Record::many_random(DIMENSION, 300);
+    for record in records.iter() {
+        collection.insert(record).unwrap();
+    }
+
+    // Deleting the entry point moves it to another vector
+    // on all the upper layers that still have vectors.
+    for i in 0..250 {
+        collection.delete(&VectorID(i)).unwrap();
+    }
+
+    // The index stays valid and searchable after the deletes.
+    let loaded = Collection::from_bytes(&collection.to_bytes()).unwrap();
+    let results = loaded.search(&records[299].vector, 10).unwrap();
+    assert_eq!(results.len(), 10);
+    assert!(results.iter().all(|result| result.id >= 250));
+}
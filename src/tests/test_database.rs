@@ -43,9 +43,352 @@ fn save_collection_update() {
     assert_eq!(db.len(), 1);
 }
 
+#[test]
+fn save_collection_in_memory() {
+    let mut db = Database::in_memory().unwrap();
+    db.save_collection(NAME, &create_collection()).unwrap();
+
+    let collection = db.get_collection(NAME).unwrap();
+    assert_eq!(collection.len(), LEN);
+    assert_eq!(db.len(), 1);
+}
+
+#[test]
+fn save_collection_file_backend() {
+    let path = "data/006";
+    let _ = remove_dir_all(path);
+
+    let backend = FileBackend::open(path).unwrap();
+    let mut db = Database::with_backend(Box::new(backend)).unwrap();
+    db.save_collection(NAME, &create_collection()).unwrap();
+
+    // Re-open the database to read the persisted collection.
+    let backend = FileBackend::open(path).unwrap();
+    let db = Database::with_backend(Box::new(backend)).unwrap();
+
+    let collection = db.get_collection(NAME).unwrap();
+    assert_eq!(collection.len(), LEN);
+    assert_eq!(db.len(), 1);
+}
+
+#[test]
+fn file_backend_keys() {
+    let path = "data/019";
+    let _ = remove_dir_all(path);
+
+    let mut backend = FileBackend::open(path).unwrap();
+    backend.put("a", b"1").unwrap();
+    backend.put("\0b", b"2").unwrap();
+    assert!(backend.contains("a").unwrap());
+    assert!(!backend.contains("c").unwrap());
+    assert_eq!(backend.len().unwrap(), 2);
+    assert_eq!(backend.keys("\0").unwrap(), vec!["\0b".to_string()]);
+
+    // Keys that don't fit in a file name are rejected.
+    let key = "k".repeat(FileBackend::MAX_KEY_LEN + 1);
+    let err = backend.put(&key, b"3").unwrap_err();
+    assert!(err.message().contains("too long"));
+    assert!(backend.get(&key).unwrap().is_none());
+
+    let key = "k".repeat(FileBackend::MAX_KEY_LEN);
+    backend.put(&key, b"3").unwrap();
+    assert_eq!(backend.get(&key).unwrap(), Some(b"3".to_vec()));
+}
+
+#[test]
+fn file_backend_apply_batch() {
+    let path = "data/020";
+    let _ = remove_dir_all(path);
+
+    let mut backend = FileBackend::open(path).unwrap();
+    backend.put("a", b"1").unwrap();
+
+    let batch = vec![("a".to_string(), None), ("b".into(), Some(b"2".into()))];
+    backend.apply_batch(batch).unwrap();
+    assert!(!backend.contains("a").unwrap());
+    assert_eq!(backend.get("b").unwrap(), Some(b"2".to_vec()));
+
+    // A batch with an invalid key writes nothing.
+    let key = "k".repeat(FileBackend::MAX_KEY_LEN + 1);
+    let batch = vec![("c".to_string(), Some(b"3".into())), (key, None)];
+    assert!(backend.apply_batch(batch).is_err());
+
+    let backend = FileBackend::open(path).unwrap();
+    assert_eq!(backend.keys("").unwrap(), vec!["b".to_string()]);
+}
+
+#[test]
+fn file_backend_recover_batch() {
+    let path = "data/021";
+    let _ = remove_dir_all(path);
+    let backend = FileBackend::open(path).unwrap();
+    drop(backend);
+
+    // Simulate a crash after the journal of the batch was written.
+    // The files are named by the hex-encoded keys.
+    std::fs::write(format!("{path}/61.tmp"), b"1").unwrap();
+    std::fs::write(format!("{path}/62"), b"2").unwrap();
+    std::fs::write(format!("{path}/journal"), "put 61\ndelete 62\n").unwrap();
+
+    // A write without a journal was never committed.
+    std::fs::write(format!("{path}/63.tmp"), b"3").unwrap();
+
+    let backend = FileBackend::open(path).unwrap();
+    assert_eq!(backend.keys("").unwrap(), vec!["a".to_string()]);
+    assert_eq!(backend.get("a").unwrap(), Some(b"1".to_vec()));
+}
+
+#[test]
+fn save_collection_flush_interval() {
+    let path = "data/007";
+    let _ = remove_dir_all(path);
+
+    let backend = SledBackend::open_with_flush_interval(path, None).unwrap();
+    let mut db = Database::with_backend(Box::new(backend)).unwrap();
+    db.save_collection(NAME, &create_collection()).unwrap();
+    db.flush().unwrap();
+
+    assert_eq!(db.get_collection(NAME).unwrap().len(), LEN);
+}
+
+#[test]
+fn apply_operations() {
+    let mut db = create_test_database("data/008");
+    let collection = create_collection();
+
+    let operations =
+        [Operation::Save("new", &collection), Operation::Delete(NAME)];
+
+    db.apply(&operations).unwrap();
+    assert_eq!(db.len(), 1);
+    assert_eq!(db.get_collection("new").unwrap().len(), LEN);
+    assert!(db.get_collection(NAME).is_err());
+}
+
+#[test]
+fn apply_operations_rollback() {
+    let mut db = create_test_database("data/009");
+    let collection = create_collection();
+
+    // The delete fails so the save is not applied either.
+    let operations =
+        [Operation::Save("new", &collection), Operation::Delete("missing")];
+
+    assert!(db.apply(&operations).is_err());
+    assert_eq!(db.len(), 1);
+    assert!(db.get_collection("new").is_err());
+}
+
+#[test]
+fn search_collections() {
+    let mut db = create_test_database("data/010");
+    db.save_collection("other", &create_collection()).unwrap();
+
+    let names = vec![NAME.to_string(), "other".to_string()];
+    let query = Vector::random(DIMENSION);
+    let results = db.search_collections(names, &query, 10).unwrap();
+
+    assert_eq!(results.len(), 10);
+
+    // The results are merged by ascending distance.
+    let distances = results.iter().map(|(_, res)| res.distance);
+    let mut pairs = distances.clone().zip(distances.skip(1));
+    assert!(pairs.all(|(a, b)| a <= b));
+
+    // A dedicated search pool returns the same results.
+    db.set_search_threads(Some(2)).unwrap();
+    let names = vec![NAME.to_string(), "other".to_string()];
+    let pooled = db.search_collections(names, &query, 10).unwrap();
+    let ids = |results: &[(String, SearchResult)]| -> Vec<u32> {
+        results.iter().map(|(_, res)| res.id).collect()
+    };
+
+    assert_eq!(ids(&pooled), ids(&results));
+}
+
+#[test]
+fn save_collection_reserved_name() {
+    let mut db = create_test_database("data/023");
+    let name = "\0staged:other";
+    assert!(db.save_collection(name, &create_collection()).is_err());
+    assert!(db.delete_collection("\0").is_err());
+
+    let operations = [Operation::Save(name, &create_collection())];
+    assert!(db.apply(&operations).is_err());
+    assert_eq!(db.len(), 1);
+}
+
+#[test]
+fn search_collections_not_found() {
+    let db = create_test_database("data/011");
+    let names = vec!["missing".to_string()];
+    let query = Vector::random(DIMENSION);
+    assert!(db.search_collections(names, &query, 10).is_err());
+}
+
 #[test]
 fn delete_collection() {
     let mut db = create_test_database("data/005");
     db.delete_collection(NAME).unwrap();
     assert_eq!(db.len(), 0);
 }
+
+#[test]
+fn promote_and_rollback_collection() {
+    let mut db = create_test_database("data/013");
+    let len = db.get_collection(NAME).unwrap().len();
+
+    // The staged version doesn't replace the current one yet.
+    let records = Record::many_random(DIMENSION, 10);
+    let staged = Collection::build(&Config::default(), &records).unwrap();
+    db.stage_collection(NAME, &staged).unwrap();
+    assert_eq!(db.get_collection(NAME).unwrap().len(), len);
+    assert_eq!(db.len(), 1);
+
+    db.promote_collection(NAME).unwrap();
+    assert_eq!(db.get_collection(NAME).unwrap().len(), 10);
+    assert!(db.promote_collection(NAME).is_err());
+
+    db.rollback_collection(NAME).unwrap();
+    assert_eq!(db.get_collection(NAME).unwrap().len(), len);
+    assert_eq!(db.len(), 1);
+}
+
+#[test]
+fn migrate_and_rollback_collection() {
+    let mut db = create_test_database("data/022");
+    let mut collection = db.get_collection(NAME).unwrap();
+    let data = HashMap::from([("count", "1")]);
+    let record = Record::new(&Vector::random(DIMENSION), &data.into());
+    let id = collection.insert(&record).unwrap();
+    db.save_collection(NAME, &collection).unwrap();
+
+    let cast = Migration::Cast("count".into(), MetadataKind::Integer);
+    assert_eq!(db.migrate_collection(NAME, &[cast]).unwrap(), 1);
+    let data = db.get_collection(NAME).unwrap().get(&id).unwrap().data;
+    assert_eq!(data.field("count"), Some(&Metadata::Integer(1)));
+
+    // The rollback restores the data before the migration.
+    db.rollback_collection(NAME).unwrap();
+    let data = db.get_collection(NAME).unwrap().get(&id).unwrap().data;
+    assert_eq!(data.field("count"), Some(&Metadata::from("1")));
+    assert_eq!(db.len(), 1);
+}
+
+#[test]
+fn rollback_collection_not_promoted() {
+    let mut db = create_test_database("data/014");
+    assert!(db.rollback_collection(NAME).is_err());
+}
+
+#[test]
+fn export_and_import_collection() {
+    let db = create_test_database("data/016");
+    let path = "data/016.bin";
+    db.export_collection(NAME, path).unwrap();
+
+    let mut other = Database::new("data/017").unwrap();
+    other.import_collection("copy", path).unwrap();
+
+    let collection = other.get_collection("copy").unwrap();
+    assert_eq!(collection.len(), LEN);
+    assert_eq!(other.len(), 1);
+}
+
+#[test]
+fn open_locked() {
+    let db = create_test_database("data/018");
+
+    // The error names the process holding the database.
+    let err = Database::open("data/018").err().unwrap();
+    let pid = std::process::id().to_string();
+    assert!(err.message().contains("in use by another process"));
+    assert!(err.message().contains(&pid));
+
+    // A database in use isn't re-created.
+    assert!(Database::new("data/018").is_err());
+    assert!(db.get_collection(NAME).is_ok());
+
+    // The lock file is kept but emptied once the database is closed.
+    drop(db);
+    let pid_file = std::fs::read_to_string("data/018/sahomedb.pid").unwrap();
+    assert!(pid_file.is_empty());
+    assert!(Database::open("data/018").is_ok());
+}
+
+#[test]
+fn get_collection_legacy() {
+    use crate::func::utils::{BaseNode, UpperNode};
+
+    // Collections were saved with bincode before the binary format.
+    let records = Record::many_random(DIMENSION, LEN);
+    let ids: Vec<VectorID> = (0..LEN as u32).map(VectorID).collect();
+    let vectors: HashMap<VectorID, Vector> = ids
+        .iter()
+        .map(|id| (*id, records[id.0 as usize].vector.clone()))
+        .collect();
+    let data: HashMap<VectorID, Metadata> = ids
+        .iter()
+        .map(|id| (*id, records[id.0 as usize].data.clone()))
+        .collect();
+
+    let config = (40_usize, 15_usize, 0.3_f32);
+    let base_layer = vec![BaseNode::default(); LEN];
+    let upper_layers: Vec<Vec<UpperNode>> = vec![];
+    let layers = (base_layer, upper_layers);
+    let legacy = (config, data, vectors, ids, layers, LEN, DIMENSION);
+    let bytes = bincode::serialize(&legacy).unwrap();
+
+    let mut backend = MemoryBackend::new();
+    backend.put(NAME, &bytes).unwrap();
+    let mut db = Database::with_backend(Box::new(backend)).unwrap();
+
+    let collection = db.get_collection(NAME).unwrap();
+    assert_eq!(collection.len(), LEN);
+    assert!(collection.unreachable_records().is_empty());
+
+    let results = collection.search(&records[5].vector, 1).unwrap();
+    assert_eq!(results[0].id, 5);
+
+    // Saving the collection stores it in the binary format.
+    db.save_collection(NAME, &collection).unwrap();
+    assert_eq!(db.get_collection(NAME).unwrap().len(), LEN);
+}
+
+#[test]
+fn get_collection_legacy_with_deletions() {
+    // Deleted records left invalid slots in the old layout.
+    let records = Record::many_random(DIMENSION, 3);
+    let ids = [VectorID(0), VectorID(2)];
+    let vectors: HashMap<VectorID, Vector> = ids
+        .iter()
+        .map(|id| (*id, records[id.0 as usize].vector.clone()))
+        .collect();
+    let data: HashMap<VectorID, Metadata> = ids
+        .iter()
+        .map(|id| (*id, records[id.0 as usize].data.clone()))
+        .collect();
+    let slots = vec![VectorID(0), VectorID(u32::MAX), VectorID(2)];
+
+    let config = (40_usize, 15_usize, 0.3_f32);
+    let layers: (Vec<u8>, Vec<u8>) = (vec![], vec![]);
+    let legacy = (config, data, vectors, slots, layers, 2_usize, DIMENSION);
+    let bytes = bincode::serialize(&legacy).unwrap();
+
+    let mut backend = MemoryBackend::new();
+    backend.put(NAME, &bytes).unwrap();
+    let db = Database::with_backend(Box::new(backend)).unwrap();
+
+    let mut collection = db.get_collection(NAME).unwrap();
+    assert_eq!(collection.len(), 2);
+    assert_eq!(collection.get(&VectorID(2)).unwrap().data, records[2].data);
+
+    // The index built over the remaining records keeps their IDs.
+    let results = collection.search(&records[2].vector, 1).unwrap();
+    assert_eq!(results[0].id, 2);
+    assert!(Collection::from_bytes(&collection.to_bytes()).is_ok());
+
+    // The slot of the deleted record is reused.
+    let id = collection.insert(&Record::random(DIMENSION)).unwrap();
+    assert_eq!(id, VectorID(1));
+}
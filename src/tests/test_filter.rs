@@ -0,0 +1,265 @@
+use super::*;
+
+/// Creates a collection with records covering each field state:
+/// set, null, missing, and data that isn't an object.
+fn create_filter_collection() -> Collection {
+    let mut records = Record::many_random(DIMENSION, 4);
+
+    let set = HashMap::from([("tag", Metadata::from("a"))]);
+    let null = HashMap::from([("tag", Metadata::Null)]);
+    let missing = HashMap::from([("other", Metadata::from("a"))]);
+
+    records[0].data = Metadata::from(set);
+    records[1].data = Metadata::from(null);
+    records[2].data = Metadata::from(missing);
+    records[3].data = Metadata::from("a");
+
+    let config = Config::default();
+    Collection::build(&config, &records).unwrap()
+}
+
+fn filtered_ids(collection: &Collection, filter: &Filter) -> Vec<u32> {
+    let vector = Vector::random(DIMENSION);
+    let results = collection.search_with_filter(&vector, 10, filter).unwrap();
+    let mut ids: Vec<u32> = results.iter().map(|r| r.id).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn filter_is_set() {
+    let collection = create_filter_collection();
+    let filter = Filter::IsSet("tag".into());
+    assert_eq!(filtered_ids(&collection, &filter), vec![0]);
+}
+
+#[test]
+fn filter_explain_pruned() {
+    let collection = create_filter_collection();
+    let vector = Vector::random(DIMENSION);
+    let filter = Filter::IsSet("tag".into());
+    let explain =
+        collection.explain_search(&vector, 10, Some(&filter)).unwrap();
+
+    assert_eq!(explain.candidates, 4);
+    assert_eq!(explain.filtered, 3);
+    assert_eq!(explain.results.len(), 1);
+}
+
+#[test]
+fn filter_is_null() {
+    let collection = create_filter_collection();
+    let filter = Filter::IsNull("tag".into());
+    assert_eq!(filtered_ids(&collection, &filter), vec![1]);
+}
+
+#[test]
+fn filter_equals_skips_missing() {
+    let collection = create_filter_collection();
+    let filter = Filter::Equals("tag".into(), Metadata::from("a"));
+    assert_eq!(filtered_ids(&collection, &filter), vec![0]);
+}
+
+#[test]
+fn filter_not_is_set() {
+    let collection = create_filter_collection();
+    let filter = Filter::Not(Box::new(Filter::IsSet("tag".into())));
+    assert_eq!(filtered_ids(&collection, &filter), vec![1, 2, 3]);
+}
+
+#[test]
+fn filter_json_dsl() {
+    let json = r#"{"bool": {
+        "must": [{"prefix": ["title", "Vector"]}],
+        "should": [
+            {"compare": ["year", "greater_or_equal", 2020]},
+            {"in": ["tag", [{"Text": "db"}, {"Text": "search"}]]}
+        ],
+        "must_not": [{"text_match": ["title", "graphs"]}]
+    }}"#;
+
+    let filter: Filter = serde_json::from_str(json).unwrap();
+    let data = |title: &str, year: usize, tag: &str| {
+        let data = HashMap::from([
+            ("title", Metadata::from(title)),
+            ("year", Metadata::from(year)),
+            ("tag", Metadata::from(tag)),
+        ]);
+
+        Metadata::from(data)
+    };
+
+    assert!(filter.matches(&data("Vector search", 2021, "ml")));
+    assert!(filter.matches(&data("Vector indexes", 2010, "db")));
+    assert!(!filter.matches(&data("Vector indexes", 2010, "ml")));
+    assert!(!filter.matches(&data("Vector Graphs", 2021, "db")));
+    assert!(!filter.matches(&data("Search vectors", 2021, "db")));
+}
+
+#[test]
+fn delete_matching() {
+    let mut collection = create_filter_collection();
+    let filter = Filter::Bool {
+        must: vec![],
+        should: vec![
+            Filter::IsSet("tag".into()),
+            Filter::IsSet("other".into()),
+        ],
+        must_not: vec![],
+    };
+
+    let deleted = collection.delete_matching(&filter).unwrap();
+    assert_eq!(deleted, vec![VectorID(0), VectorID(2)]);
+    assert_eq!(collection.len(), 2);
+}
+
+/// Creates a collection with records located in Berlin, Potsdam,
+/// and Munich, and a record without a location.
+fn create_geo_collection() -> Collection {
+    let mut records = Record::many_random(DIMENSION, 4);
+    let locations = [(52.52, 13.405), (52.39, 13.065), (48.137, 11.575)];
+
+    for (record, (lat, lon)) in records.iter_mut().zip(locations) {
+        let point = HashMap::from([("lat", lat), ("lon", lon)]);
+        let data = HashMap::from([("location", Metadata::from(point))]);
+        record.data = Metadata::from(data);
+    }
+
+    let config = Config::default();
+    Collection::build(&config, &records).unwrap()
+}
+
+#[test]
+fn filter_geo_radius() {
+    let mut collection = create_geo_collection();
+    let berlin = GeoPoint::new(52.52, 13.405);
+    let filter = Filter::GeoRadius("location".into(), berlin, 50_000.0);
+    assert_eq!(filtered_ids(&collection, &filter), vec![0, 1]);
+
+    // The spatial index must return the same records.
+    collection.create_geo_index("location");
+    assert_eq!(filtered_ids(&collection, &filter), vec![0, 1]);
+}
+
+#[test]
+fn filter_geo_radius_antimeridian() {
+    let mut records = Record::many_random(DIMENSION, 3);
+    let locations = [(0.0, 179.95), (0.0, -179.95), (0.0, 170.0)];
+    for (record, (lat, lon)) in records.iter_mut().zip(locations) {
+        let point = HashMap::from([("lat", lat), ("lon", lon)]);
+        let data = HashMap::from([("location", Metadata::from(point))]);
+        record.data = Metadata::from(data);
+    }
+
+    let config = Config::default();
+    let mut collection = Collection::build(&config, &records).unwrap();
+
+    // The circle crosses the antimeridian.
+    let center = GeoPoint::new(0.0, 179.9);
+    let filter = Filter::GeoRadius("location".into(), center, 50_000.0);
+    assert_eq!(filtered_ids(&collection, &filter), vec![0, 1]);
+
+    collection.create_geo_index("location");
+    assert_eq!(filtered_ids(&collection, &filter), vec![0, 1]);
+
+    // The area from 179 to -179 degrees crosses it too.
+    let top_left = GeoPoint::new(1.0, 179.0);
+    let bottom_right = GeoPoint::new(-1.0, -179.0);
+    let area = GeoBoundingBox::new(top_left, bottom_right);
+    let filter = Filter::GeoBoundingBox("location".into(), area);
+    assert_eq!(filtered_ids(&collection, &filter), vec![0, 1]);
+}
+
+#[test]
+fn filter_geo_bounding_box() {
+    let mut collection = create_geo_collection();
+    let top_left = GeoPoint::new(53.0, 13.2);
+    let bottom_right = GeoPoint::new(52.0, 14.0);
+    let area = GeoBoundingBox::new(top_left, bottom_right);
+    let filter = Filter::GeoBoundingBox("location".into(), area);
+    assert_eq!(filtered_ids(&collection, &filter), vec![0]);
+
+    collection.create_geo_index("location");
+    assert_eq!(filtered_ids(&collection, &filter), vec![0]);
+
+    // Both sides of the antimeridian cover the cell of the edges.
+    let top_left = GeoPoint::new(53.0, 13.3);
+    let bottom_right = GeoPoint::new(48.0, 13.1);
+    let area = GeoBoundingBox::new(top_left, bottom_right);
+    let filter = Filter::GeoBoundingBox("location".into(), area);
+    assert_eq!(filtered_ids(&collection, &filter), vec![0, 1, 2]);
+}
+
+#[test]
+fn geo_search() {
+    let mut collection = create_geo_collection();
+    collection.create_geo_index("location");
+
+    // Insert a record in Hamburg after creating the index.
+    let point = HashMap::from([("lat", 53.551), ("lon", 9.994)]);
+    let data = HashMap::from([("location", Metadata::from(point))]);
+    let record = Record::new(&Vector::random(DIMENSION), &data.into());
+    collection.insert(&record).unwrap();
+
+    let munich = GeoPoint::new(48.137, 11.575);
+    let results = collection.geo_search(&munich, 10).unwrap();
+    let ids: Vec<u32> = results.iter().map(|r| r.id).collect();
+    assert_eq!(ids, vec![2, 1, 0, 4]);
+    assert!(results[0].distance < 1.0);
+
+    // Berlin to Potsdam is about 27 km.
+    let berlin = GeoPoint::new(52.52, 13.405);
+    let potsdam = GeoPoint::new(52.39, 13.065);
+    let distance = berlin.distance(&potsdam);
+    assert!((distance - 27_000.0).abs() < 1_000.0);
+}
+
+#[test]
+fn filter_oversampled() {
+    // Only every twentieth record matches the filter.
+    let mut records = Record::many_random(DIMENSION, LEN);
+    for (i, record) in records.iter_mut().enumerate() {
+        let tag = Metadata::from(if i % 20 == 0 { "a" } else { "b" });
+        record.data = Metadata::from(HashMap::from([("tag", tag)]));
+    }
+
+    let config = Config::default();
+    let collection = Collection::build(&config, &records).unwrap();
+    let vector = Vector::random(DIMENSION);
+    let filter = Filter::Equals("tag".into(), Metadata::from("a"));
+
+    let explain =
+        collection.explain_oversampled(&vector, 5, Some(&filter), LEN).unwrap();
+
+    assert_eq!(explain.results.len(), 5);
+    assert!(explain.ef > config.ef_search);
+}
+
+#[test]
+fn filter_planned() {
+    let mut records = Record::many_random(8, 5000);
+    for (i, record) in records.iter_mut().enumerate() {
+        let data = HashMap::from([
+            ("parity", Metadata::from(i % 2)),
+            ("bucket", Metadata::from(i % 100)),
+        ]);
+        record.data = Metadata::from(data);
+    }
+
+    let collection = Collection::build(&Config::default(), &records).unwrap();
+    let even = Filter::Equals("parity".into(), Metadata::from(0usize));
+    let rare = Filter::Equals("bucket".into(), Metadata::from(0usize));
+    let all = Filter::IsSet("parity".into());
+
+    assert_eq!(collection.plan_filter(10, &all), FilterStrategy::PostFilter);
+    assert_eq!(collection.plan_filter(10, &rare), FilterStrategy::Exact);
+    let strategy = collection.plan_filter(10, &even);
+    assert!(matches!(strategy, FilterStrategy::Oversample(_)));
+
+    let vector = Vector::random(8);
+    for filter in [even, rare, all] {
+        let results = collection.search_planned(&vector, 10, &filter).unwrap();
+        assert_eq!(results.len(), 10);
+        assert!(results.iter().all(|r| filter.matches(&r.data)));
+    }
+}
@@ -0,0 +1,70 @@
+use super::*;
+
+const DAY: usize = 86400;
+
+/// Creates a collection with daily partitions and a record
+/// at the start and the middle of each of the three days.
+fn create_partitioned_collection() -> PartitionedCollection {
+    let config = Config::default();
+    let mut collection =
+        PartitionedCollection::new(&config, "time", DAY).unwrap();
+
+    let times = [0, DAY / 2, DAY, DAY * 3 / 2, DAY * 2, DAY * 5 / 2];
+    for time in times {
+        let mut record = Record::random(DIMENSION);
+        let data = HashMap::from([("time", Metadata::from(time))]);
+        record.data = Metadata::from(data);
+        collection.insert(&record).unwrap();
+    }
+
+    collection
+}
+
+fn result_times(results: &[(usize, SearchResult)]) -> Vec<usize> {
+    let time = |(_, res): &(usize, SearchResult)| match res.data.field("time") {
+        Some(Metadata::Integer(time)) => *time,
+        _ => panic!("The result has no timestamp."),
+    };
+
+    let mut times: Vec<usize> = results.iter().map(time).collect();
+    times.sort();
+    times
+}
+
+#[test]
+fn partition_insert_routes_by_time() {
+    let collection = create_partitioned_collection();
+    assert_eq!(collection.partitions(), vec![0, DAY, DAY * 2]);
+    assert_eq!(collection.partition(DAY).unwrap().len(), 2);
+    assert_eq!(collection.len(), 6);
+}
+
+#[test]
+fn partition_insert_without_time() {
+    let mut collection = create_partitioned_collection();
+    let record = Record::random(DIMENSION);
+    assert!(collection.insert(&record).is_err());
+}
+
+#[test]
+fn partition_search_time_range() {
+    let collection = create_partitioned_collection();
+    let vector = Vector::random(DIMENSION);
+
+    let all = collection.search(&vector, 10, None, None).unwrap();
+    assert_eq!(all.len(), 6);
+
+    // The range covers the second half of day 0 and day 1 fully.
+    let start = Some(DAY / 2);
+    let end = Some(DAY * 2);
+    let results = collection.search(&vector, 10, start, end).unwrap();
+    assert_eq!(result_times(&results), vec![DAY / 2, DAY, DAY * 3 / 2]);
+}
+
+#[test]
+fn partition_drop_before() {
+    let mut collection = create_partitioned_collection();
+    assert_eq!(collection.drop_before(DAY * 3 / 2), 1);
+    assert_eq!(collection.partitions(), vec![DAY, DAY * 2]);
+    assert_eq!(collection.len(), 4);
+}